@@ -0,0 +1,319 @@
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::Sha256;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 指令签名校验配置，对应 `config.toml` 中的 `[auth]` 段
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    /// 是否只接受带有效签名的指令
+    pub enabled: bool,
+    /// 共享密钥
+    pub key: Option<String>,
+    /// 保存共享密钥的文件，与 `key` 二选一
+    pub key_file: Option<PathBuf>,
+    /// 签名时间戳与本机时间允许相差的秒数
+    pub max_age_secs: u64,
+    /// 记录已使用 nonce 的数量上限
+    pub nonce_cache_size: usize,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            key: None,
+            key_file: None,
+            max_age_secs: 300,
+            nonce_cache_size: 1024,
+        }
+    }
+}
+
+impl AuthConfig {
+    /// 将相对路径解析为相对于配置文件所在目录的路径
+    pub fn resolve_paths(&mut self, base: &Path) {
+        if let Some(path) = &mut self.key_file {
+            if path.is_relative() {
+                *path = base.join(&*path);
+            }
+        }
+    }
+
+    /// 读取共享密钥
+    ///
+    /// # Returns
+    /// * `Ok(Some(Vec<u8>))` - 启用签名校验时的密钥
+    /// * `Ok(None)` - 未启用签名校验
+    /// * `Err(String)` - 密钥配置错误或密钥文件无法读取
+    pub fn key(&self) -> Result<Option<Vec<u8>>, String> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        if self.max_age_secs == 0 {
+            return Err("[auth] max_age_secs 必须大于 0".to_string());
+        }
+        if self.nonce_cache_size == 0 {
+            return Err("[auth] nonce_cache_size 必须大于 0".to_string());
+        }
+        let key = match (&self.key, &self.key_file) {
+            (Some(key), None) => key.clone(),
+            (None, Some(path)) => std::fs::read_to_string(path)
+                .map_err(|e| format!("无法读取密钥文件 {:?}: {}", path, e))?
+                .trim()
+                .to_string(),
+            (Some(_), Some(_)) => return Err("[auth] key 与 key_file 只能配置一个".to_string()),
+            (None, None) => return Err("启用 [auth] 时需要配置 key 或 key_file".to_string()),
+        };
+        if key.is_empty() {
+            return Err("[auth] 共享密钥不能为空".to_string());
+        }
+        Ok(Some(key.into_bytes()))
+    }
+}
+
+/// 已使用的 nonce，在签名有效期内拒绝重复的 nonce
+///
+/// 超过容量时丢弃最早记录的 nonce。
+#[derive(Debug, Default)]
+pub struct NonceCache {
+    /// nonce 及其过期时间
+    seen: HashMap<String, DateTime<Utc>>,
+    /// 按记录顺序排列的 nonce
+    order: VecDeque<String>,
+    capacity: usize,
+}
+
+impl NonceCache {
+    /// 调整容量，超出的 nonce 按记录顺序丢弃
+    pub fn set_capacity(&mut self, capacity: usize) {
+        self.capacity = capacity;
+        self.shrink(capacity);
+    }
+
+    fn shrink(&mut self, len: usize) {
+        while self.order.len() > len {
+            if let Some(nonce) = self.order.pop_front() {
+                self.seen.remove(&nonce);
+            }
+        }
+    }
+
+    /// 记录 nonce
+    ///
+    /// # Returns
+    /// * `false` - nonce 已在有效期内使用过
+    fn insert(&mut self, nonce: &str, expires: DateTime<Utc>, now: DateTime<Utc>) -> bool {
+        self.seen.retain(|_, expiry| *expiry >= now);
+        let seen = &self.seen;
+        self.order.retain(|nonce| seen.contains_key(nonce));
+        if self.seen.contains_key(nonce) {
+            return false;
+        }
+        self.shrink(self.capacity.saturating_sub(1));
+        self.seen.insert(nonce.to_string(), expires);
+        self.order.push_back(nonce.to_string());
+        true
+    }
+}
+
+/// 各层对象都按键名排序的 JSON 值
+///
+/// 不依赖 `serde_json::Map` 的键顺序：启用 `preserve_order` 特性后它按插入顺序排列。
+#[derive(Serialize)]
+#[serde(untagged)]
+enum Canonical {
+    Object(BTreeMap<String, Canonical>),
+    Array(Vec<Canonical>),
+    Scalar(Value),
+}
+
+impl From<Value> for Canonical {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Object(map) => Canonical::Object(map.into_iter().map(|(k, v)| (k, v.into())).collect()),
+            Value::Array(items) => Canonical::Array(items.into_iter().map(Canonical::from).collect()),
+            scalar => Canonical::Scalar(scalar),
+        }
+    }
+}
+
+/// 计算签名的规范内容：去掉 `sig` 字段后各层按键名排序、不含空白的 JSON
+fn canonical_payload(mut message: serde_json::Map<String, Value>) -> String {
+    message.remove("sig");
+    serde_json::to_string(&Canonical::from(Value::Object(message))).unwrap_or_default()
+}
+
+/// 校验指令签名
+///
+/// 签名的指令是一个 JSON 对象，除指令字段外还包含 `nonce`（字符串）、
+/// `ts`（RFC3339 字符串或 Unix 毫秒时间戳）与 `sig`（十六进制的 HMAC-SHA256），
+/// 签名内容为去掉 `sig` 后各层对象均按键名排序、不含空白的 JSON。
+/// 同时带有 `params.ts` 时，两者必须表示同一时间，过期检查才与签名校验的时间一致。
+///
+/// # Arguments
+/// * `config` - `[auth]` 配置
+/// * `key` - 共享密钥
+/// * `nonces` - 已使用的 nonce
+/// * `payload` - 收到的消息内容
+/// * `now` - 当前时间
+///
+/// # Returns
+/// * `Err(String)` - 未签名、签名无效、时间戳超出有效期或不一致、nonce 重复
+pub fn verify(
+    config: &AuthConfig,
+    key: &[u8],
+    nonces: &mut NonceCache,
+    payload: &[u8],
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    let message = match serde_json::from_slice::<Value>(payload) {
+        Ok(Value::Object(message)) => message,
+        _ => return Err("未签名的指令（不是 JSON 对象）".to_string()),
+    };
+    let sig = match message.get("sig") {
+        Some(Value::String(sig)) => hex::decode(sig).map_err(|_| "签名不是十六进制字符串".to_string())?,
+        Some(_) => return Err("签名不是十六进制字符串".to_string()),
+        None => return Err("未签名的指令（缺少 sig）".to_string()),
+    };
+    let nonce = match message.get("nonce") {
+        Some(Value::String(nonce)) if !nonce.is_empty() => nonce.clone(),
+        _ => return Err("签名的指令缺少 nonce".to_string()),
+    };
+    let ts = message.get("ts").ok_or("签名的指令缺少 ts")?;
+    let sent = crate::timestamp::parse(ts).map_err(|e| format!("ts {}", e))?;
+    if let Some(params_ts) = message.get("params").and_then(|p| p.get("ts")) {
+        let params_sent = crate::timestamp::parse(params_ts).map_err(|e| format!("params.ts {}", e))?;
+        if params_sent != sent {
+            return Err(format!(
+                "params.ts ({}) 与签名的 ts ({}) 不一致",
+                params_sent.to_rfc3339(),
+                sent.to_rfc3339()
+            ));
+        }
+    }
+
+    let mut mac = HmacSha256::new_from_slice(key).map_err(|e| e.to_string())?;
+    mac.update(canonical_payload(message).as_bytes());
+    mac.verify_slice(&sig).map_err(|_| "签名无效".to_string())?;
+
+    let max_age = chrono::Duration::seconds(config.max_age_secs as i64);
+    if (now - sent).abs() > max_age {
+        return Err(format!("签名时间戳超出有效期 (ts: {})", sent.to_rfc3339()));
+    }
+    if !nonces.insert(&nonce, sent + max_age, now) {
+        return Err(format!("重复的 nonce，可能是重放的指令: '{}'", nonce));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use serde_json::json;
+
+    /// 按 Python 示例的方式签名
+    fn sign(key: &[u8], message: Value) -> Vec<u8> {
+        let Value::Object(mut message) = message else { unreachable!() };
+        let mut mac = HmacSha256::new_from_slice(key).unwrap();
+        mac.update(canonical_payload(message.clone()).as_bytes());
+        message.insert("sig".to_string(), hex::encode(mac.finalize().into_bytes()).into());
+        Value::Object(message).to_string().into_bytes()
+    }
+
+    #[test]
+    fn nonce_cache_expiry_and_eviction() {
+        let now = Utc::now();
+        let mut nonces = NonceCache::default();
+        nonces.set_capacity(2);
+
+        assert!(nonces.insert("a", now + Duration::seconds(10), now));
+        assert!(!nonces.insert("a", now + Duration::seconds(10), now + Duration::seconds(5)));
+        // 过期后的 nonce 被清理，可以再次使用
+        assert!(nonces.insert("a", now + Duration::seconds(60), now + Duration::seconds(11)));
+        assert_eq!(nonces.order, ["a"]);
+
+        // 超过容量时丢弃最早记录的 nonce
+        assert!(nonces.insert("b", now + Duration::seconds(60), now + Duration::seconds(11)));
+        assert!(nonces.insert("c", now + Duration::seconds(60), now + Duration::seconds(11)));
+        assert_eq!(nonces.order, ["b", "c"]);
+        assert_eq!(nonces.seen.len(), 2);
+
+        nonces.set_capacity(1);
+        assert_eq!(nonces.order, ["c"]);
+        assert_eq!(nonces.seen.len(), 1);
+    }
+
+    #[test]
+    fn verify_signed_commands() {
+        let config = AuthConfig::default();
+        let mut nonces = NonceCache::default();
+        nonces.set_capacity(config.nonce_cache_size);
+        let now = Utc::now();
+        let ts = now.to_rfc3339();
+
+        let payload = sign(b"secret", json!({"action": "on", "nonce": "n1", "ts": ts}));
+        verify(&config, b"secret", &mut nonces, &payload, now).unwrap();
+        let err = verify(&config, b"secret", &mut nonces, &payload, now).unwrap_err();
+        assert!(err.contains("重复的 nonce"), "{}", err);
+
+        let payload = sign(b"other", json!({"action": "on", "nonce": "n2", "ts": ts}));
+        assert_eq!(verify(&config, b"secret", &mut nonces, &payload, now).unwrap_err(), "签名无效");
+
+        let old = now - Duration::seconds(config.max_age_secs as i64 + 1);
+        let payload = sign(b"secret", json!({"action": "on", "nonce": "n3", "ts": old.to_rfc3339()}));
+        let err = verify(&config, b"secret", &mut nonces, &payload, now).unwrap_err();
+        assert!(err.contains("超出有效期"), "{}", err);
+
+        let err = verify(&config, b"secret", &mut nonces, br#"{"action":"on"}"#, now).unwrap_err();
+        assert!(err.contains("缺少 sig"), "{}", err);
+    }
+
+    #[test]
+    fn verify_requires_matching_params_ts() {
+        let config = AuthConfig::default();
+        let mut nonces = NonceCache::default();
+        nonces.set_capacity(config.nonce_cache_size);
+        let now = Utc::now();
+
+        // 同一时间的不同写法视为一致
+        let millis = now.timestamp_millis();
+        let rfc3339 = DateTime::from_timestamp_millis(millis).unwrap().to_rfc3339();
+        let payload = sign(
+            b"secret",
+            json!({"action": "on", "nonce": "n1", "ts": millis, "params": {"ts": rfc3339}}),
+        );
+        verify(&config, b"secret", &mut nonces, &payload, now).unwrap();
+
+        // 签名时间有效，但 params.ts 是一个早已过去的时间
+        let stale = (now - Duration::days(1)).to_rfc3339();
+        let payload = sign(
+            b"secret",
+            json!({"action": "on", "nonce": "n2", "ts": now.to_rfc3339(), "params": {"ts": stale}}),
+        );
+        let err = verify(&config, b"secret", &mut nonces, &payload, now).unwrap_err();
+        assert!(err.contains("不一致"), "{}", err);
+    }
+
+    #[test]
+    fn canonical_payload_sorts_keys_recursively() {
+        let message = json!({
+            "sig": "00",
+            "params": {"source": "pir", "level": 30, "display": [{"b": 1, "a": 2}]},
+            "nonce": "n1",
+            "action": "on",
+        });
+        let Value::Object(message) = message else { unreachable!() };
+        assert_eq!(
+            canonical_payload(message),
+            r#"{"action":"on","nonce":"n1","params":{"display":[{"a":2,"b":1}],"level":30,"source":"pir"}}"#
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use rumqttc::v5;
+use rumqttc::v5::mqttbytes::v5::{ConnectProperties, Filter, LastWill as LastWillV5, Packet, PublishProperties};
+use rumqttc::{LastWill, Outgoing, QoS, SubscribeFilter, Transport};
+use serde::Deserialize;
+use std::time::Duration;
+
+/// MQTT 协议版本
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+pub enum ProtocolVersion {
+    /// MQTT 3.1.1
+    #[default]
+    #[serde(rename = "3.1.1")]
+    V311,
+    /// MQTT 5，支持 Response Topic、Correlation Data 与 User Property
+    #[serde(rename = "5")]
+    V5,
+}
+
+/// 建立连接所需的参数，两种协议版本共用
+pub struct ConnectOptions {
+    pub client_id: String,
+    /// Broker 地址（WebSocket 传输时为完整 URL）
+    pub broker_addr: String,
+    pub port: u16,
+    pub transport: Transport,
+    pub keep_alive: Duration,
+    pub clean_session: bool,
+    /// 持久会话在断开后保留的时间（秒），仅 MQTT 5 且 `clean_session` 为 `false` 时使用
+    pub session_expiry_secs: u32,
+    pub inflight: u16,
+    pub max_packet_size: usize,
+    pub credentials: Option<(String, String)>,
+    /// 遗嘱消息的主题与内容（QoS 1，保留消息）
+    pub last_will: (String, String),
+}
+
+/// 收到的 PUBLISH 消息
+pub struct Publish {
+    pub topic: String,
+    pub payload: Vec<u8>,
+    /// MQTT 5 的 Response Topic
+    pub response_topic: Option<String>,
+    /// MQTT 5 的 Correlation Data
+    pub correlation_data: Option<Vec<u8>>,
+    /// MQTT 5 的 User Property
+    pub user_properties: Vec<(String, String)>,
+}
+
+impl Publish {
+    /// 读取指定名称的 User Property
+    pub fn user_property(&self, name: &str) -> Option<&str> {
+        self.user_properties
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+/// 事件循环产生的事件
+pub enum Event {
+    /// 收到 Broker 的 CONNACK，连接已建立
+    Connected {
+        /// Broker 是否保留了上次的会话
+        session_present: bool,
+    },
+    /// 收到消息
+    Publish(Publish),
+    /// Broker 主动断开连接
+    Disconnect,
+    /// 本端的 DISCONNECT 已发送
+    DisconnectSent,
+    /// 其他事件
+    Other,
+}
+
+/// MQTT 客户端，按协议版本封装 rumqttc 的两套客户端
+#[derive(Clone)]
+pub enum Client {
+    V311(rumqttc::AsyncClient),
+    V5(v5::AsyncClient),
+}
+
+/// MQTT 事件循环
+pub enum EventLoop {
+    V311(Box<rumqttc::EventLoop>),
+    V5(Box<v5::EventLoop>),
+}
+
+/// 创建客户端与事件循环
+///
+/// # Arguments
+/// * `protocol` - 使用的协议版本
+/// * `options` - 连接参数
+/// * `cap` - 请求队列容量
+pub fn connect(protocol: ProtocolVersion, options: ConnectOptions, cap: usize) -> (Client, EventLoop) {
+    let (will_topic, will_payload) = options.last_will;
+    match protocol {
+        ProtocolVersion::V311 => {
+            let mut mqtt_options = rumqttc::MqttOptions::new(options.client_id, options.broker_addr, options.port);
+            mqtt_options.set_transport(options.transport);
+            mqtt_options.set_keep_alive(options.keep_alive);
+            mqtt_options.set_clean_session(options.clean_session);
+            mqtt_options.set_inflight(options.inflight);
+            mqtt_options.set_max_packet_size(options.max_packet_size, options.max_packet_size);
+            mqtt_options.set_last_will(LastWill::new(will_topic, will_payload, QoS::AtLeastOnce, true));
+            if let Some((username, password)) = options.credentials {
+                mqtt_options.set_credentials(username, password);
+            }
+            let (client, eventloop) = rumqttc::AsyncClient::new(mqtt_options, cap);
+            (Client::V311(client), EventLoop::V311(Box::new(eventloop)))
+        }
+        ProtocolVersion::V5 => {
+            let mut mqtt_options = v5::MqttOptions::new(options.client_id, options.broker_addr, options.port);
+            mqtt_options.set_transport(options.transport);
+            mqtt_options.set_keep_alive(options.keep_alive);
+            mqtt_options.set_clean_start(options.clean_session);
+            // MQTT 5 的会话默认随连接结束，持久会话需要设置保留时间
+            if !options.clean_session {
+                let mut properties = ConnectProperties::new();
+                properties.session_expiry_interval = Some(options.session_expiry_secs);
+                mqtt_options.set_connect_properties(properties);
+            }
+            mqtt_options.set_receive_maximum(Some(options.inflight));
+            mqtt_options.set_outgoing_inflight_upper_limit(options.inflight);
+            mqtt_options.set_max_packet_size(Some(options.max_packet_size as u32));
+            mqtt_options.set_last_will(LastWillV5::new(will_topic, will_payload, qos_v5(QoS::AtLeastOnce), true, None));
+            if let Some((username, password)) = options.credentials {
+                mqtt_options.set_credentials(username, password);
+            }
+            let (client, eventloop) = v5::AsyncClient::new(mqtt_options, cap);
+            (Client::V5(client), EventLoop::V5(Box::new(eventloop)))
+        }
+    }
+}
+
+fn qos_v5(qos: QoS) -> v5::mqttbytes::QoS {
+    match qos {
+        QoS::AtMostOnce => v5::mqttbytes::QoS::AtMostOnce,
+        QoS::AtLeastOnce => v5::mqttbytes::QoS::AtLeastOnce,
+        QoS::ExactlyOnce => v5::mqttbytes::QoS::ExactlyOnce,
+    }
+}
+
+impl Client {
+    /// 发布消息
+    pub async fn publish(&self, topic: &str, qos: QoS, retain: bool, payload: impl Into<Vec<u8>>) -> Result<(), String> {
+        let payload = payload.into();
+        match self {
+            Client::V311(client) => client.publish(topic, qos, retain, payload).await.map_err(|e| e.to_string()),
+            Client::V5(client) => client
+                .publish(topic, qos_v5(qos), retain, payload)
+                .await
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    /// 发布回复消息，MQTT 5 下带上请求的 Correlation Data
+    pub async fn publish_reply(
+        &self,
+        topic: &str,
+        payload: impl Into<Vec<u8>>,
+        correlation_data: Option<Vec<u8>>,
+    ) -> Result<(), String> {
+        let payload = payload.into();
+        match self {
+            Client::V311(client) => client
+                .publish(topic, QoS::AtLeastOnce, false, payload)
+                .await
+                .map_err(|e| e.to_string()),
+            Client::V5(client) => {
+                let properties = PublishProperties {
+                    correlation_data: correlation_data.map(Into::into),
+                    content_type: Some("application/json".to_string()),
+                    ..Default::default()
+                };
+                client
+                    .publish_with_properties(topic, qos_v5(QoS::AtLeastOnce), false, payload, properties)
+                    .await
+                    .map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    /// 订阅多个主题
+    pub async fn subscribe_many(&self, filters: &[String], qos: QoS) -> Result<(), String> {
+        match self {
+            Client::V311(client) => client
+                .subscribe_many(filters.iter().map(|filter| SubscribeFilter::new(filter.clone(), qos)))
+                .await
+                .map_err(|e| e.to_string()),
+            Client::V5(client) => client
+                .subscribe_many(filters.iter().map(|filter| Filter::new(filter.clone(), qos_v5(qos))))
+                .await
+                .map_err(|e| e.to_string()),
+        }
+    }
+
+    /// 断开连接
+    pub async fn disconnect(&self) -> Result<(), String> {
+        match self {
+            Client::V311(client) => client.disconnect().await.map_err(|e| e.to_string()),
+            Client::V5(client) => client.disconnect().await.map_err(|e| e.to_string()),
+        }
+    }
+}
+
+impl EventLoop {
+    /// 驱动事件循环，返回下一个事件
+    pub async fn poll(&mut self) -> Result<Event, String> {
+        match self {
+            EventLoop::V311(eventloop) => match eventloop.poll().await.map_err(|e| e.to_string())? {
+                rumqttc::Event::Incoming(rumqttc::Incoming::Publish(p)) => Ok(Event::Publish(Publish {
+                    topic: p.topic,
+                    payload: p.payload.to_vec(),
+                    response_topic: None,
+                    correlation_data: None,
+                    user_properties: Vec::new(),
+                })),
+                rumqttc::Event::Incoming(rumqttc::Incoming::ConnAck(ack)) => Ok(Event::Connected {
+                    session_present: ack.session_present,
+                }),
+                rumqttc::Event::Incoming(rumqttc::Incoming::Disconnect) => Ok(Event::Disconnect),
+                rumqttc::Event::Outgoing(Outgoing::Disconnect) => Ok(Event::DisconnectSent),
+                _ => Ok(Event::Other),
+            },
+            EventLoop::V5(eventloop) => match eventloop.poll().await.map_err(|e| e.to_string())? {
+                v5::Event::Incoming(Packet::Publish(p)) => {
+                    let properties = p.properties.unwrap_or_default();
+                    Ok(Event::Publish(Publish {
+                        topic: String::from_utf8_lossy(&p.topic).into_owned(),
+                        payload: p.payload.to_vec(),
+                        response_topic: properties.response_topic,
+                        correlation_data: properties.correlation_data.map(|data| data.to_vec()),
+                        user_properties: properties.user_properties,
+                    }))
+                }
+                v5::Event::Incoming(Packet::ConnAck(ack)) => Ok(Event::Connected {
+                    session_present: ack.session_present,
+                }),
+                v5::Event::Incoming(Packet::Disconnect(_)) => Ok(Event::Disconnect),
+                v5::Event::Outgoing(Outgoing::Disconnect) => Ok(Event::DisconnectSent),
+                _ => Ok(Event::Other),
+            },
+        }
+    }
+}
@@ -0,0 +1,167 @@
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::mqtt::{self, MqttConfig};
+use crate::screen::Capabilities;
+
+/// Home Assistant 使用的开关指令内容
+pub const PAYLOAD_ON: &str = "ON";
+pub const PAYLOAD_OFF: &str = "OFF";
+
+/// Home Assistant MQTT 自动发现配置，对应 `config.toml` 中的 `[homeassistant]` 段
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct HomeAssistantConfig {
+    /// 是否在连接后发布自动发现配置
+    pub enabled: bool,
+    /// Home Assistant 的自动发现前缀
+    pub discovery_prefix: String,
+    /// 设备名称，未设置时使用主机名
+    pub device_name: Option<String>,
+}
+
+impl Default for HomeAssistantConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            discovery_prefix: "homeassistant".to_string(),
+            device_name: None,
+        }
+    }
+}
+
+/// Home Assistant 实体使用的指令主题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HomeAssistantTopics {
+    /// 开关指令主题（`ON` / `OFF`）
+    pub power: String,
+    /// 亮度指令主题（0-100 的数值）
+    pub brightness: String,
+}
+
+impl HomeAssistantConfig {
+    /// 检查自动发现配置是否有效
+    pub fn validate(&self, mqtt: &MqttConfig) -> Result<(), String> {
+        if !self.enabled {
+            return Ok(());
+        }
+        if self.discovery_prefix.is_empty() || !rumqttc::valid_topic(&self.discovery_prefix) {
+            return Err(format!("[homeassistant] discovery_prefix 无效: '{}'", self.discovery_prefix));
+        }
+        if self.topics(mqtt).is_none() {
+            return Err("启用 Home Assistant 自动发现时，[mqtt] command_topics 中至少需要一个不含通配符的主题".to_string());
+        }
+        Ok(())
+    }
+
+    /// Home Assistant 实体的指令主题：使用第一个不含通配符的指令主题，
+    /// 亮度指令使用其下的 `brightness` 子主题
+    pub fn topics(&self, mqtt: &MqttConfig) -> Option<HomeAssistantTopics> {
+        let power = mqtt
+            .command_filters()
+            .into_iter()
+            .find(|topic| !rumqttc::has_wildcards(topic))?;
+        Some(HomeAssistantTopics {
+            brightness: format!("{}/brightness", power),
+            power,
+        })
+    }
+}
+
+/// 把客户端 ID 转换为 Home Assistant 允许的节点 ID（字母、数字、`_` 和 `-`）
+fn node_id(client_id: &str) -> String {
+    client_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+        .collect()
+}
+
+/// 生成自动发现消息
+///
+/// 发布一个控制电源的 `switch`、一个表示连接状态的 `binary_sensor`，
+/// 后端支持亮度调节时再发布一个 `light`。
+///
+/// # Arguments
+/// * `client_id` - 展开后的 MQTT 客户端 ID，用作设备标识
+/// * `backend_name` - 当前显示后端名称，作为设备型号
+/// * `caps` - 当前显示后端的能力
+///
+/// # Returns
+/// * `Vec<(String, String)>` - 自动发现主题与内容；后端不支持亮度时 `light` 的内容为空，用于删除旧实体
+pub fn discovery_messages(
+    config: &HomeAssistantConfig,
+    mqtt: &MqttConfig,
+    topics: &HomeAssistantTopics,
+    client_id: &str,
+    backend_name: &str,
+    caps: Capabilities,
+) -> Vec<(String, String)> {
+    let node = node_id(client_id);
+    let device_name = config.device_name.clone().unwrap_or_else(mqtt::hostname);
+    let device = json!({
+        "identifiers": [node.clone()],
+        "name": device_name,
+        "manufacturer": "Auto Screen Switch",
+        "model": backend_name,
+        "sw_version": env!("CARGO_PKG_VERSION"),
+    });
+    let state_topic = mqtt.state_topic();
+    let availability_topic = mqtt.availability_topic();
+    let config_topic = |component: &str, object: &str| {
+        format!("{}/{}/{}/{}/config", config.discovery_prefix, component, node, object)
+    };
+
+    let switch = json!({
+        "name": "Screen",
+        "unique_id": format!("{}_screen", node),
+        "device": device,
+        "command_topic": topics.power,
+        "payload_on": PAYLOAD_ON,
+        "payload_off": PAYLOAD_OFF,
+        "state_topic": state_topic,
+        "value_template": "{{ value_json.state | upper }}",
+        "state_on": PAYLOAD_ON,
+        "state_off": PAYLOAD_OFF,
+        "availability_topic": availability_topic,
+        "icon": "mdi:monitor",
+    });
+
+    let light = caps.brightness.then(|| {
+        json!({
+            "name": "Screen Brightness",
+            "unique_id": format!("{}_brightness", node),
+            "device": device,
+            "command_topic": topics.power,
+            "payload_on": PAYLOAD_ON,
+            "payload_off": PAYLOAD_OFF,
+            "state_topic": state_topic,
+            "state_value_template": "{{ value_json.state | upper }}",
+            "brightness_command_topic": topics.brightness,
+            "brightness_state_topic": state_topic,
+            "brightness_value_template": "{{ value_json.brightness }}",
+            "brightness_scale": 100,
+            "availability_topic": availability_topic,
+            "icon": "mdi:monitor",
+        })
+    });
+
+    // 连接状态本身就是在线状态主题，不再设置 availability，否则离线时显示为“不可用”而不是“断开”
+    let connectivity = json!({
+        "name": "Connectivity",
+        "unique_id": format!("{}_connectivity", node),
+        "device": device,
+        "device_class": "connectivity",
+        "state_topic": availability_topic,
+        "payload_on": crate::AVAILABILITY_ONLINE,
+        "payload_off": crate::AVAILABILITY_OFFLINE,
+    });
+
+    vec![
+        (config_topic("switch", "screen"), switch.to_string()),
+        (
+            config_topic("light", "brightness"),
+            light.map(|l| l.to_string()).unwrap_or_default(),
+        ),
+        (config_topic("binary_sensor", "connectivity"), connectivity.to_string()),
+    ]
+}
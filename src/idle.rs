@@ -0,0 +1,84 @@
+use std::time::Duration;
+
+#[cfg(windows)]
+use windows::Win32::System::SystemInformation::GetTickCount;
+#[cfg(windows)]
+use windows::Win32::UI::Input::KeyboardAndMouse::{GetLastInputInfo, LASTINPUTINFO};
+#[cfg(target_os = "linux")]
+use x11rb::connection::{Connection, RequestConnection};
+#[cfg(target_os = "linux")]
+use x11rb::protocol::screensaver::{self, ConnectionExt as _};
+#[cfg(target_os = "linux")]
+use x11rb::protocol::xproto::Window;
+#[cfg(target_os = "linux")]
+use x11rb::rust_connection::RustConnection;
+
+/// 本地用户输入检测
+///
+/// 读取距离最后一次键盘/鼠标输入的时间，用于熄屏宽限期内判断是否有人在使用电脑。
+/// Windows 使用 `GetLastInputInfo`；Linux 使用 X11 MIT-SCREEN-SAVER 扩展，
+/// 无法连接 X Server 时不支持检测。
+pub struct InputMonitor {
+    #[cfg(target_os = "linux")]
+    x11: Option<(RustConnection, Window)>,
+}
+
+#[cfg(windows)]
+impl InputMonitor {
+    pub fn new(_x_display: Option<&str>) -> Self {
+        Self {}
+    }
+
+    /// 距离最后一次用户输入的时间，无法检测时返回 `None`
+    pub fn idle_time(&self) -> Option<Duration> {
+        unsafe {
+            let mut info = LASTINPUTINFO {
+                cbSize: std::mem::size_of::<LASTINPUTINFO>() as u32,
+                dwTime: 0,
+            };
+            if !GetLastInputInfo(&mut info).as_bool() {
+                return None;
+            }
+            // 两者都是开机以来的毫秒数，约 49.7 天回绕一次
+            Some(Duration::from_millis(GetTickCount().wrapping_sub(info.dwTime) as u64))
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl InputMonitor {
+    /// 连接 X Server 并检查 MIT-SCREEN-SAVER 扩展是否可用
+    ///
+    /// # Arguments
+    /// * `x_display` - X11 显示名称，未设置时使用 `DISPLAY` 环境变量
+    pub fn new(x_display: Option<&str>) -> Self {
+        let x11 = x11rb::connect(x_display).ok().and_then(|(conn, screen)| {
+            conn.extension_information(screensaver::X11_EXTENSION_NAME).ok()??;
+            let root = conn.setup().roots.get(screen)?.root;
+            Some((conn, root))
+        });
+        if x11.is_none() {
+            crate::log_warn("无法通过 X11 检测用户输入，熄屏宽限期内只有 on 指令能取消熄屏");
+        }
+        Self { x11 }
+    }
+
+    /// 距离最后一次用户输入的时间，无法检测时返回 `None`
+    pub fn idle_time(&self) -> Option<Duration> {
+        let (conn, root) = self.x11.as_ref()?;
+        let info = conn.screensaver_query_info(*root).ok()?.reply().ok()?;
+        Some(Duration::from_millis(info.ms_since_user_input as u64))
+    }
+}
+
+#[cfg(not(any(windows, target_os = "linux")))]
+impl InputMonitor {
+    pub fn new(_x_display: Option<&str>) -> Self {
+        Self {}
+    }
+
+    /// 当前平台不支持检测用户输入
+    pub fn idle_time(&self) -> Option<Duration> {
+        None
+    }
+}
@@ -1,21 +1,33 @@
 #![windows_subsystem = "windows"] // 隐藏控制台窗口
 
-use rumqttc::{AsyncClient, Event, Incoming, MqttOptions, QoS};
+use rumqttc::QoS;
 use serde::Deserialize;
 use serde_json::Value;
 use std::fs::{self, OpenOptions};
 use std::io::{Write, ErrorKind};
 use std::path::Path;
-use std::sync::{mpsc as std_mpsc, Mutex};
+use std::sync::{mpsc as std_mpsc, Arc, Mutex};
 use std::time::{Duration, SystemTime, UNIX_EPOCH, Instant};
 use tokio::sync::mpsc;
+#[cfg(windows)]
 use tray_icon::menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem};
+#[cfg(windows)]
 use tray_icon::{Icon, TrayIconBuilder};
+#[cfg(windows)]
 use winit::event_loop::{ControlFlow, EventLoop};
 use single_instance::SingleInstance;
 
 mod screen;
+mod idle;
+mod mqtt;
+mod client;
+mod homeassistant;
+mod auth;
+mod policy;
+mod timestamp;
+#[cfg(windows)]
 mod autostart;
+#[cfg(windows)]
 mod icon;
 
 /// MQTT 配置结构体，从 `config.toml` 文件加载
@@ -29,6 +41,63 @@ struct Config {
     username: Option<String>,
     /// MQTT 密码（可选）
     password: Option<String>,
+    /// 传输方式："tcp"（默认）、"ws" 或 "wss"
+    #[serde(default)]
+    transport: mqtt::TransportKind,
+    /// WebSocket 连接的 URL 路径
+    #[serde(default = "default_ws_path")]
+    ws_path: String,
+    /// MQTT 主题与客户端标识配置（可选）
+    #[serde(default)]
+    mqtt: mqtt::MqttConfig,
+    /// TLS 配置（可选）
+    #[serde(default)]
+    tls: mqtt::TlsConfig,
+    /// 备用 Broker（可选），按顺序在连接失败时切换
+    #[serde(default)]
+    brokers: Vec<mqtt::BrokerConfig>,
+    /// 重连策略（可选）
+    #[serde(default)]
+    reconnect: mqtt::ReconnectConfig,
+    /// 指令签名校验配置（可选）
+    #[serde(default)]
+    auth: auth::AuthConfig,
+    /// 按来源限制指令的规则（可选）
+    #[serde(default)]
+    policy: policy::PolicyConfig,
+    /// Home Assistant 自动发现配置（可选）
+    #[serde(default)]
+    homeassistant: homeassistant::HomeAssistantConfig,
+    /// 显示控制配置（可选，默认按平台自动选择后端）
+    #[serde(default)]
+    display: screen::DisplayConfig,
+}
+
+fn default_ws_path() -> String {
+    "/mqtt".to_string()
+}
+
+impl Config {
+    /// 按连接顺序排列的 Broker：顶层配置的 Broker 在前，`[[brokers]]` 在后
+    fn brokers(&self) -> Vec<mqtt::Broker> {
+        let primary = mqtt::BrokerConfig {
+            host: self.broker_ip.clone(),
+            port: Some(self.broker_port),
+            ..Default::default()
+        };
+        std::iter::once(&primary)
+            .chain(&self.brokers)
+            .map(|broker| mqtt::Broker {
+                host: broker.host.clone(),
+                port: broker.port.unwrap_or(self.broker_port),
+                username: broker.username.clone().or_else(|| self.username.clone()),
+                password: broker.password.clone().or_else(|| self.password.clone()),
+                transport: broker.transport.unwrap_or(self.transport),
+                ws_path: broker.ws_path.clone().unwrap_or_else(|| self.ws_path.clone()),
+                tls: broker.tls.clone().unwrap_or_else(|| self.tls.clone()),
+            })
+            .collect()
+    }
 }
 
 /// MQTT 消息结构体，适配新的 JSON 格式
@@ -36,6 +105,54 @@ struct Config {
 struct MqttMessage {
     action: String,
     params: Option<Value>,
+    /// 指令 ID，原样带回确认消息
+    id: Option<Value>,
+    /// 确认消息的发布主题，未设置时使用 `[mqtt] ack_topic`
+    reply_to: Option<String>,
+    /// 签名指令的发送时间，未带 `params.ts` 时用于过期检查
+    ts: Option<Value>,
+}
+
+/// 解析收到的指令
+///
+/// 除 JSON 格式的 `MqttMessage` 外，还接受 Home Assistant 发送的
+/// `ON` / `OFF` 纯文本指令，以及亮度指令主题上的 0-100 数值。
+///
+/// # Arguments
+/// * `topic` - 消息所在的主题
+/// * `payload` - 消息内容
+/// * `brightness_topic` - Home Assistant 亮度指令主题（未启用自动发现时为 `None`）
+fn parse_command(topic: &str, payload: &[u8], brightness_topic: Option<&str>) -> Result<MqttMessage, String> {
+    let text = String::from_utf8_lossy(payload);
+    let text = text.trim();
+    let params = |extra: Value| {
+        let mut params = serde_json::json!({ "source": "homeassistant" });
+        if let (Some(params), Value::Object(extra)) = (params.as_object_mut(), extra) {
+            params.extend(extra);
+        }
+        Some(params)
+    };
+
+    if brightness_topic == Some(topic) {
+        let level: u8 = text.parse().map_err(|_| format!("亮度指令不是 0-100 的数值: '{}'", text))?;
+        return Ok(MqttMessage {
+            action: "brightness".to_string(),
+            params: params(serde_json::json!({ "level": level })),
+            id: None,
+            reply_to: None,
+            ts: None,
+        });
+    }
+    if text.eq_ignore_ascii_case(homeassistant::PAYLOAD_ON) || text.eq_ignore_ascii_case(homeassistant::PAYLOAD_OFF) {
+        return Ok(MqttMessage {
+            action: text.to_ascii_lowercase(),
+            params: params(Value::Null),
+            id: None,
+            reply_to: None,
+            ts: None,
+        });
+    }
+    serde_json::from_slice::<MqttMessage>(payload).map_err(|e| format!("JSON 解析失败: {}", e))
 }
 
 /// 连接状态枚举
@@ -57,6 +174,8 @@ struct ConnectionStats {
     last_disconnection_time: Option<Instant>,
     total_uptime: Duration,
     current_uptime: Option<Instant>,
+    /// 当前使用的 Broker
+    active_broker: Option<String>,
 }
 
 impl ConnectionStats {
@@ -69,6 +188,7 @@ impl ConnectionStats {
             last_disconnection_time: None,
             total_uptime: Duration::ZERO,
             current_uptime: None,
+            active_broker: None,
         }
     }
 
@@ -78,6 +198,13 @@ impl ConnectionStats {
         self.current_uptime = Some(Instant::now());
     }
 
+    fn set_active_broker(&mut self, broker: String) {
+        if self.active_broker.as_ref() != Some(&broker) {
+            log_info(&format!("🔀 当前 MQTT Broker: {}", broker));
+            self.active_broker = Some(broker);
+        }
+    }
+
     fn on_connection_success(&mut self) {
         self.successful_connections += 1;
         log_info(&format!("✅ MQTT 连接成功 (第 {} 次)", self.successful_connections));
@@ -115,8 +242,9 @@ impl ConnectionStats {
             0.0
         };
         
-        format!("总连接次数: {}, 成功率: {:.1}%, 总运行时间: {}小时{}分钟", 
-                self.total_connections, success_rate, total_hours, total_minutes)
+        format!("总连接次数: {}, 成功率: {:.1}%, 总运行时间: {}小时{}分钟, 当前 Broker: {}", 
+                self.total_connections, success_rate, total_hours, total_minutes,
+                self.active_broker.as_deref().unwrap_or("无"))
     }
 }
 
@@ -246,6 +374,118 @@ broker_port = 1883
 
 # MQTT 密码（可选，如果不需要认证请注释掉）
 # password = "your_password"
+
+# 传输方式（可选）："tcp"（默认）、"ws"（WebSocket）或 "wss"（基于 TLS 的 WebSocket，证书配置见 [tls]）
+# transport = "tcp"
+# ws_path = "/mqtt"    # WebSocket 连接的 URL 路径
+
+# 备用 Broker（可选，可配置多个）：连接失败或断开时按顺序切换，未设置的字段沿用上面的配置
+# [[brokers]]
+# host = "192.168.1.101"
+# port = 1883
+# username = "backup_user"
+# password = "backup_password"
+# transport = "tcp"
+# [brokers.tls]         # 该 Broker 单独的 TLS 配置，未设置时沿用 [tls]
+# enabled = true
+# ca_file = "backup-ca.crt"
+
+# MQTT 主题与客户端 ID（可选）
+# [mqtt]
+# protocol = "3.1.1"                            # 协议版本："3.1.1" 或 "5"（支持 Response Topic 请求/回复）
+# client_id = "auto_screen_switch_{hostname}"   # 支持 {hostname}、{username}、{machine_id} 占位符
+# topic_prefix = ""                             # 本节点的主题前缀（如 "office/pc1"），会加在下面所有主题之前
+# command_topics = ["actuator/autoScreenSwitch"] # 订阅的指令主题，支持 + / # 通配符
+# response_topic = "actuator/autoScreenSwitch/response" # enumerate 等查询指令的回复主题
+# state_topic = "actuator/autoScreenSwitch/state"     # 屏幕状态主题（保留消息）
+# state_poll_secs = 30                          # 定期检查屏幕状态的间隔（秒），为 0 时只在执行指令后发布
+# availability_topic = "actuator/autoScreenSwitch/availability" # 在线状态主题（online / offline，保留消息）
+# ack_topic = ""                                # 指令执行结果的默认确认主题（如 "actuator/autoScreenSwitch/ack"），为空时只回复带 reply_to 的指令
+# reply_topics = []                            # 允许作为 reply_to 的主题（支持通配符，不加前缀），为空时只允许 topic_prefix 下的主题
+# qos = 0                                       # 订阅指令主题的 QoS（0、1 或 2）
+# clean_session = true                          # 设为 false 并使用 qos = 1 时，Broker 会缓存断线期间的指令，重连后按顺序补发
+# session_expiry_secs = 3600                    # 持久会话在断开后保留的时间（秒），仅 MQTT 5
+# keep_alive_secs = 60                          # 保活间隔（秒，最大 65535），为 0 时不发送心跳
+# inflight = 100                                # 同时等待确认的消息数上限
+# max_packet_size = 102400                      # 收发消息的最大字节数
+# max_command_age_secs = 0                      # 指令最长有效时间（秒），按 params.ts（或签名的 ts）判断，过期的指令被忽略；为 0 时不检查
+# max_clock_skew_secs = 30                      # 允许 params.ts 比本机时间超前的秒数
+
+# TLS 加密连接（可选，启用后 broker_port 通常为 8883；transport = "wss" 时总是使用 TLS，只读取这里的证书配置）
+# [tls]
+# enabled = true
+# ca_file = "ca.crt"          # CA 证书（PEM），相对路径相对于本文件所在目录，未设置时使用系统根证书
+# client_cert = "client.crt"  # 客户端证书（PEM），与 client_key 同时配置时启用双向 TLS
+# client_key = "client.key"   # 客户端私钥（PEM）
+# alpn = ["mqtt"]             # ALPN 协议（可选）
+# insecure_skip_verify = false # 跳过服务器证书校验，仅用于实验环境
+
+# 断线重连策略（可选）
+# [reconnect]
+# max_retries = 10        # 连续失败多少次后停止重连，为 0 时一直重连（适合无人值守的设备）
+# initial_delay_secs = 1  # 第一次重连前的等待时间（秒），之后每次翻倍
+# max_delay_secs = 60     # 重连等待时间的上限（秒）
+# jitter = 0.0            # 随机抖动比例（0-1），避免多个节点同时重连
+
+# 按来源（params.source）限制指令（可选），未带 source 的指令来源为 "unknown"
+# [policy]
+# allow_unknown_sources = true   # 设为 false 时只接受下面配置的来源
+# [policy.sources.idle_timeout]
+# actions = ["off"]              # 允许的指令，为空时允许全部
+# [policy.sources.pir_motion]
+# actions = ["on", "off"]
+# cooldown_secs = 5              # 同一来源两次控制指令之间的最短间隔（秒）
+# [policy.sources.manual]
+# priority = 10                  # 优先级，数值越大越优先（默认 0）
+# hold_secs = 600                # 执行后优先级更低的来源在这段时间（秒）内不能控制屏幕
+
+# 指令签名校验（可选）：启用后只接受带 HMAC-SHA256 签名的指令，未签名、签名无效或重放的指令被拒绝
+# [auth]
+# enabled = true
+# key_file = "auth.key"   # 共享密钥文件，相对路径相对于本文件所在目录；也可以用 key = "..." 直接配置
+# max_age_secs = 300      # 签名时间戳与本机时间允许相差的秒数
+# nonce_cache_size = 1024 # 记录已使用 nonce 的数量上限
+
+# Home Assistant MQTT 自动发现（可选）
+# [homeassistant]
+# enabled = true
+# discovery_prefix = "homeassistant"
+# device_name = "办公室电脑"   # 未设置时使用主机名
+
+# 显示控制后端（可选）："auto" 按平台自动选择，"win32" 使用 Windows 广播，
+# "dpms" 使用 X11 DPMS，"backlight" 使用 sysfs 背光，"ddc" 使用 DDC/CI
+# [display]
+# backend = "auto"
+# settle_ms = 1000     # 开关操作后等待状态稳定的时间（毫秒），之后读回状态确认是否生效
+# retries = 2          # 屏幕状态未改变时的重试次数
+
+# 熄屏前调暗（可选）：收到 off 后先调暗并等待宽限期，期间有人操作电脑或收到 on 则取消熄屏
+# [display.dim]
+# grace_secs = 30      # 宽限期（秒），为 0 时立即熄屏
+# level = 10           # 宽限期内的亮度（0-100）
+# fade_ms = 1000       # 调暗与恢复亮度的渐变时长（毫秒）
+
+# 开关屏幕防抖（可选）：传感器抖动时避免显示器反复开关，只作用于整体开关屏幕的指令
+# [display.debounce]
+# coalesce_ms = 500    # 合并窗口（毫秒），窗口内收到多条开关指令时只执行最后一条
+# min_on_secs = 30     # 开启后至少保持开启的时间（秒），期间收到的 off 延迟到期满后执行
+# min_off_secs = 10    # 关闭后至少保持关闭的时间（秒），期间收到的 on 延迟到期满后执行
+
+# X11 DPMS 后端配置（可选，仅 Linux）
+# [display.dpms]
+# display = ":0"
+# off_level = "off"    # 关闭屏幕时的电源等级："standby"、"suspend" 或 "off"
+
+# sysfs 背光后端配置（可选，仅 Linux）
+# [display.backlight]
+# sysfs_root = "/sys/class/backlight"
+# devices = []         # 只控制指定的设备（如 ["intel_backlight"]），为空时控制全部
+
+# DDC/CI 后端配置（可选，仅 Linux，需要 i2c-dev 内核模块）
+# [display.ddc]
+# buses = []           # 使用的 I2C 总线（如 ["/dev/i2c-4"]），为空时扫描全部总线
+# off_mode = "off"     # 关闭屏幕时的电源模式："standby"、"suspend"、"off" 或 "hard_off"
+# on_input = 15        # 开启屏幕后切换到的输入源（VCP 0x60 取值，可选）
 "#;
                 match fs::write(&config_file, default_content) {
                     Ok(_) => {
@@ -270,7 +510,7 @@ broker_port = 1883
         }
     };
     
-    let config: Config = match toml::from_str(&content) {
+    let mut config: Config = match toml::from_str(&content) {
         Ok(config) => {
             log_info("配置文件格式解析成功");
             config
@@ -282,18 +522,44 @@ broker_port = 1883
         }
     };
     
-    if config.broker_ip.is_empty() {
-        let msg = "MQTT Broker IP 地址不能为空".to_string();
+    if let Err(msg) = config.mqtt.validate() {
+        log_error(&msg);
+        return Err(msg);
+    }
+    if let Err(msg) = config.homeassistant.validate(&config.mqtt) {
         log_error(&msg);
         return Err(msg);
     }
-    if config.broker_port == 0 {
-        let msg = "MQTT Broker 端口号不能为 0".to_string();
+    if let Err(msg) = config.reconnect.validate() {
         log_error(&msg);
         return Err(msg);
     }
+    if let Err(msg) = config.policy.validate() {
+        log_error(&msg);
+        return Err(msg);
+    }
+    config.auth.resolve_paths(config_dir);
+    if let Err(msg) = config.auth.key() {
+        log_error(&msg);
+        return Err(msg);
+    }
+    if config.auth.enabled && config.homeassistant.enabled {
+        log_warn("启用 [auth] 后 Home Assistant 发送的未签名指令会被拒绝");
+    }
+    config.tls.resolve_paths(config_dir);
+    for tls in config.brokers.iter_mut().filter_map(|broker| broker.tls.as_mut()) {
+        tls.resolve_paths(config_dir);
+    }
+    let brokers = config.brokers();
+    for broker in &brokers {
+        if let Err(msg) = broker.validate() {
+            log_error(&msg);
+            return Err(msg);
+        }
+    }
     
-    let info_msg = format!("📋 配置加载完成 - Broker: {}:{}", config.broker_ip, config.broker_port);
+    let names: Vec<String> = brokers.iter().map(|broker| broker.name()).collect();
+    let info_msg = format!("📋 配置加载完成 - Broker: {}", names.join(", "));
     log_info(&info_msg);
     
     Ok(config)
@@ -302,7 +568,53 @@ broker_port = 1883
 /// MQTT 消息处理
 enum MqttCommand {
     Start,
+    /// 只有托盘菜单会发送
+    #[cfg_attr(not(windows), allow(dead_code))]
     Stop,
+    /// 程序退出：发布离线状态后结束 MQTT 任务
+    Quit,
+}
+
+/// 在线状态主题的消息内容
+pub(crate) const AVAILABILITY_ONLINE: &str = "online";
+pub(crate) const AVAILABILITY_OFFLINE: &str = "offline";
+
+/// 当前的 MQTT 连接，停止或退出时用来发布离线状态
+struct MqttSession {
+    client: client::Client,
+    eventloop: client::EventLoop,
+    availability_topic: String,
+}
+
+impl MqttSession {
+    /// 主动发布离线状态并断开连接
+    ///
+    /// 遗嘱消息只在异常断开时由 Broker 发布，正常停止或退出时需要自己发布 `offline`。
+    async fn close(mut self) {
+        if let Err(e) = self
+            .client
+            .publish(&self.availability_topic, QoS::AtLeastOnce, true, AVAILABILITY_OFFLINE)
+            .await
+        {
+            log_warn(&format!("发布离线状态失败: {}", e));
+            return;
+        }
+        let _ = self.client.disconnect().await;
+
+        // 驱动事件循环，把离线消息和 DISCONNECT 发送出去
+        let flush = async {
+            loop {
+                match self.eventloop.poll().await {
+                    Ok(client::Event::DisconnectSent) | Err(_) => break,
+                    Ok(_) => {}
+                }
+            }
+        };
+        match tokio::time::timeout(Duration::from_secs(2), flush).await {
+            Ok(()) => log_info(&format!("📴 已发布离线状态: {}", self.availability_topic)),
+            Err(_) => log_warn("发布离线状态超时"),
+        }
+    }
 }
 
 /// MQTT 运行状态（从后台任务回传到主线程，用于同步托盘按钮状态）
@@ -312,17 +624,492 @@ enum MqttStatus {
     Error(String),
 }
 
+/// 执行屏幕开关指令
+///
+/// 屏幕控制包含等待状态稳定与重试的阻塞延时，
+/// 放到阻塞线程池中执行，避免占用异步运行时的工作线程。
+///
+/// # Arguments
+/// * `selectors` - `params.display` 指定的目标显示器，`None` 表示全部显示器
+///
+/// # Returns
+/// * `Result<bool, String>` - 是否执行了操作，或重试后屏幕状态仍未改变时的错误信息
+async fn run_power_command(
+    backend: Arc<dyn screen::DisplayBackend>,
+    display_cfg: screen::DisplayConfig,
+    selectors: Option<Vec<screen::DisplaySelector>>,
+    on: bool,
+) -> Result<bool, String> {
+    tokio::task::spawn_blocking(move || match selectors {
+        Some(selectors) => {
+            let displays = screen::resolve_displays(backend.as_ref(), &selectors)?;
+            screen::set_displays_smart(backend.as_ref(), &displays, on, &display_cfg)
+        }
+        None => screen::set_display_smart(backend.as_ref(), on, &display_cfg),
+    })
+        .await
+        .map_err(|e| format!("屏幕控制任务异常退出: {}", e))?
+}
+
+/// 在阻塞线程池中读取当前屏幕状态
+async fn query_display_state(backend: Arc<dyn screen::DisplayBackend>) -> screen::ScreenState {
+    tokio::task::spawn_blocking(move || screen::get_display_state(backend.as_ref()))
+        .await
+        .unwrap_or(screen::ScreenState::Unknown)
+}
+
+/// 已发布的屏幕状态与亮度
+type PublishedState = (screen::ScreenState, Option<u8>);
+
+/// 屏幕状态发布器
+///
+/// 把当前屏幕状态、触发来源和时间戳以保留消息发布到状态主题，
+/// 让 Pi5 与仪表盘随时能读到最新状态。
+#[derive(Clone)]
+struct StatePublisher {
+    client: client::Client,
+    topic: String,
+    /// 最后一次发布的状态与亮度
+    last: Arc<Mutex<Option<PublishedState>>>,
+}
+
+impl StatePublisher {
+    fn new(client: client::Client, topic: String) -> Self {
+        Self {
+            client,
+            topic,
+            last: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// 读取当前屏幕状态并发布
+    ///
+    /// # Arguments
+    /// * `source` - 触发发布的来源（指令的 `params.source`、"connect" 或 "system"）
+    /// * `only_if_changed` - 为 `true` 时状态与上次发布的相同则不发布
+    async fn publish(&self, backend: Arc<dyn screen::DisplayBackend>, source: &str, only_if_changed: bool) {
+        let result = tokio::task::spawn_blocking(move || {
+            let state = screen::get_display_state(backend.as_ref());
+            // 支持亮度的后端同时报告亮度，供 Home Assistant 的 light 实体使用
+            let brightness = if backend.capabilities().brightness {
+                backend.brightness().ok()
+            } else {
+                None
+            };
+            (state, brightness)
+        })
+        .await;
+        let (state, brightness) = match result {
+            Ok(result) => result,
+            Err(e) => {
+                log_error(&format!("屏幕状态检测任务异常退出: {}", e));
+                return;
+            }
+        };
+        {
+            let mut last = self.last.lock().unwrap();
+            if only_if_changed && last.as_ref() == Some(&(state.clone(), brightness)) {
+                return;
+            }
+            *last = Some((state.clone(), brightness));
+        }
+
+        let mut payload = serde_json::json!({
+            "state": state,
+            "source": source,
+            "timestamp": chrono::Local::now().to_rfc3339(),
+        });
+        if let Some(brightness) = brightness {
+            payload["brightness"] = brightness.into();
+        }
+        match self.client.publish(&self.topic, QoS::AtLeastOnce, true, payload.to_string()).await {
+            Ok(_) => log_info(&format!("📤 屏幕状态已发布: {:?} (来源: {})", state, source)),
+            Err(e) => log_warn(&format!("发布屏幕状态失败: {}", e)),
+        }
+    }
+}
+
+/// 指令执行结果
+enum CommandResult {
+    /// 执行了操作
+    Executed,
+    /// 屏幕已处于目标状态等原因，无需操作
+    NoOp(String),
+    /// 执行失败
+    Error(String),
+}
+
+/// 指令回复（确认消息）
+///
+/// 指令执行完成后把结果发布到回复主题：MQTT 5 请求的 Response Topic（连同
+/// Correlation Data）、指令中的 `reply_to` 或 `[mqtt] ack_topic`，依次优先。
+struct Reply {
+    client: client::Client,
+    backend: Arc<dyn screen::DisplayBackend>,
+    topic: String,
+    correlation_data: Option<Vec<u8>>,
+    /// 指令 ID
+    id: Value,
+    /// 指令名称，解析失败时为 `None`
+    action: Option<String>,
+    /// 执行前的屏幕状态，解析失败时为 `None`
+    previous_state: Option<screen::ScreenState>,
+}
+
+impl Reply {
+    fn new(
+        client: client::Client,
+        backend: Arc<dyn screen::DisplayBackend>,
+        topic: String,
+        correlation_data: Option<Vec<u8>>,
+    ) -> Self {
+        Self {
+            client,
+            backend,
+            topic,
+            correlation_data,
+            id: Value::Null,
+            action: None,
+            previous_state: None,
+        }
+    }
+
+    /// 发布执行结果
+    async fn send(self, result: CommandResult) {
+        self.send_with(result, Value::Null).await;
+    }
+
+    /// 发布执行结果，`extra` 中的字段（如 `enumerate` 的显示器列表）合并到回复中
+    async fn send_with(self, result: CommandResult, extra: Value) {
+        let mut payload = match extra {
+            Value::Object(extra) => extra,
+            _ => serde_json::Map::new(),
+        };
+        let new_state = match self.previous_state {
+            Some(_) => Some(query_display_state(self.backend.clone()).await),
+            None => None,
+        };
+        let (result, reason, error) = match result {
+            CommandResult::Executed => ("executed", None, None),
+            CommandResult::NoOp(reason) => ("no_op", Some(reason), None),
+            CommandResult::Error(error) => ("error", None, Some(error)),
+        };
+        payload.insert("id".to_string(), self.id.clone());
+        payload.insert("action".to_string(), self.action.clone().into());
+        payload.insert("result".to_string(), result.into());
+        payload.insert("previous_state".to_string(), serde_json::json!(self.previous_state));
+        payload.insert("new_state".to_string(), serde_json::json!(new_state));
+        payload.insert("error".to_string(), error.into());
+        if let Some(reason) = reason {
+            payload.insert("reason".to_string(), reason.into());
+        }
+        match self
+            .client
+            .publish_reply(&self.topic, Value::Object(payload).to_string(), self.correlation_data)
+            .await
+        {
+            Ok(()) => log_info(&format!("↩️ 指令结果已回复到 {}: {}", self.topic, result)),
+            Err(e) => log_warn(&format!("回复指令结果失败: {}", e)),
+        }
+    }
+
+    /// 从共享槽位中取出回复并发布，槽位为空时说明已经回复过
+    async fn send_from(slot: &Mutex<Option<Reply>>, result: CommandResult) {
+        let reply = slot.lock().unwrap().take();
+        if let Some(reply) = reply {
+            reply.send(result).await;
+        }
+    }
+}
+
+/// 正在进行的亮度渐变、熄屏宽限期或延迟执行的开关指令任务
+struct PendingTask {
+    handle: tokio::task::JoinHandle<()>,
+    /// 任务完成后要发送的回复，任务被中断时由中断方发送
+    reply: Arc<Mutex<Option<Reply>>>,
+}
+
+impl PendingTask {
+    /// 在后台运行任务，完成后把结果回复给请求方
+    fn spawn<F>(reply: Option<Reply>, task: F) -> Self
+    where
+        F: std::future::Future<Output = CommandResult> + Send + 'static,
+    {
+        let reply = Arc::new(Mutex::new(reply));
+        let slot = reply.clone();
+        let handle = tokio::spawn(async move {
+            let result = task.await;
+            Reply::send_from(&slot, result).await;
+        });
+        Self { handle, reply }
+    }
+
+    /// 中断任务，尚未回复的请求以 `no_op` 回复
+    async fn interrupt(self) {
+        if !self.handle.is_finished() {
+            log_info("⏹️ 中断正在进行的亮度渐变、熄屏宽限期或延迟执行的开关指令");
+        }
+        self.handle.abort();
+        Reply::send_from(&self.reply, CommandResult::NoOp("被新指令中断".to_string())).await;
+    }
+}
+
+/// 屏幕开关指令及其执行所需的上下文，可以立即执行，也可以作为后台任务延迟执行
+struct PowerCommand {
+    backend: Arc<dyn screen::DisplayBackend>,
+    display_cfg: screen::DisplayConfig,
+    /// `params.display` 指定的目标显示器，`None` 表示全部显示器
+    selectors: Option<Vec<screen::DisplaySelector>>,
+    /// 目标状态：`true` 表示开启屏幕
+    on: bool,
+    source: String,
+    state_publisher: StatePublisher,
+    status_tx: std_mpsc::Sender<MqttStatus>,
+    /// 熄屏前调暗时记录的原亮度
+    restore_brightness: Arc<Mutex<Option<u8>>>,
+    debouncer: Arc<Mutex<screen::Debouncer>>,
+    policy_cfg: policy::PolicyConfig,
+    policy_state: Arc<Mutex<policy::PolicyState>>,
+}
+
+impl PowerCommand {
+    /// 是否先调暗并等待宽限期再熄屏，只适用于整体熄屏
+    fn uses_grace(&self) -> bool {
+        !self.on && self.display_cfg.dim.grace_secs > 0 && self.selectors.is_none()
+    }
+
+    /// 执行开关指令
+    async fn run(self) -> CommandResult {
+        // 防抖等待结束、真正开始执行时才计入来源的冷却时间与保持期
+        self.policy_state.lock().unwrap().record(&self.policy_cfg, &self.source, Instant::now());
+        let name = if self.on { "开启" } else { "关闭" };
+        let outcome = if self.uses_grace() {
+            run_dim_then_off(self.backend.clone(), self.display_cfg.clone(), self.restore_brightness.clone()).await
+        } else {
+            // 使用智能屏幕控制，避免重复操作
+            run_power_command(self.backend.clone(), self.display_cfg.clone(), self.selectors.clone(), self.on).await
+        };
+        let result = match outcome {
+            Ok(true) => {
+                log_info(&format!("✅ 屏幕{}操作完成", name));
+                if self.selectors.is_none() {
+                    self.debouncer.lock().unwrap().record(self.on, Instant::now());
+                }
+                self.state_publisher.publish(self.backend.clone(), &self.source, false).await;
+                CommandResult::Executed
+            }
+            Ok(false) if self.uses_grace() => {
+                CommandResult::NoOp("屏幕已经处于关闭状态，或宽限期内检测到用户操作".to_string())
+            }
+            Ok(false) => {
+                log_info(&format!("ℹ️ 屏幕已经处于{}状态，无需操作", name));
+                CommandResult::NoOp(format!("屏幕已经处于{}状态", name))
+            }
+            Err(e) => {
+                let error_msg = format!("❌ 屏幕{}操作失败: {}", name, e);
+                log_error(&error_msg);
+                let _ = self.status_tx.send(MqttStatus::Error(error_msg));
+                CommandResult::Error(e)
+            }
+        };
+
+        // 熄屏前调暗过亮度时恢复原亮度
+        if self.on {
+            let level = self.restore_brightness.lock().unwrap().take();
+            if let Some(level) = level {
+                match run_brightness_command(self.backend.clone(), None, level, 0).await {
+                    Ok(()) => log_info(&format!("💡 已恢复亮度 {}%", level)),
+                    Err(e) => log_warn(&format!("恢复亮度失败: {}", e)),
+                }
+            }
+        }
+        result
+    }
+}
+
+/// 亮度渐变的步进间隔
+const FADE_STEP: Duration = Duration::from_millis(50);
+
+/// 执行亮度调节指令
+///
+/// 在 `fade_ms` 内从当前亮度分步过渡到目标亮度，`fade_ms` 为 0 时直接设置。
+/// 每一步的读写都放到阻塞线程池中执行；调用方把它作为独立任务运行，
+/// 新指令到达时通过 `JoinHandle::abort` 在两步之间中断渐变。
+/// 指定显示器时从第一台目标显示器的当前亮度开始渐变。
+///
+/// # Arguments
+/// * `selectors` - `params.display` 指定的目标显示器，`None` 表示全部显示器
+/// * `level` - 目标亮度（0-100）
+/// * `fade_ms` - 渐变时长（毫秒）
+async fn run_brightness_command(
+    backend: Arc<dyn screen::DisplayBackend>,
+    selectors: Option<Vec<screen::DisplaySelector>>,
+    level: u8,
+    fade_ms: u64,
+) -> Result<(), String> {
+    let displays = match selectors {
+        Some(selectors) => {
+            let backend = backend.clone();
+            let displays = tokio::task::spawn_blocking(move || screen::resolve_displays(backend.as_ref(), &selectors))
+                .await
+                .map_err(|e| format!("显示器枚举任务异常退出: {}", e))??;
+            Some(displays)
+        }
+        None => None,
+    };
+    let set = |value: u8| {
+        let backend = backend.clone();
+        let displays = displays.clone();
+        async move {
+            tokio::task::spawn_blocking(move || match displays {
+                Some(displays) => backend.set_brightness_for(&displays, value),
+                None => backend.set_brightness(value),
+            })
+                .await
+                .map_err(|e| format!("亮度调节任务异常退出: {}", e))?
+        }
+    };
+
+    if fade_ms == 0 {
+        return set(level).await;
+    }
+
+    // 从各目标显示器的平均亮度开始渐变
+    let start = {
+        let backend = backend.clone();
+        let displays = displays.clone();
+        tokio::task::spawn_blocking(move || match displays {
+            Some(displays) => screen::mean_brightness(displays.iter().map(|&display| backend.brightness_for(display))),
+            None => backend.brightness(),
+        })
+            .await
+            .map_err(|e| format!("亮度读取任务异常退出: {}", e))??
+    };
+    let steps = (fade_ms / FADE_STEP.as_millis() as u64).max(1) as i64;
+    let mut last = start;
+    for step in 1..=steps {
+        let value = (start as i64 + (level as i64 - start as i64) * step / steps) as u8;
+        if value != last {
+            set(value).await?;
+            last = value;
+        }
+        if step < steps {
+            tokio::time::sleep(FADE_STEP).await;
+        }
+    }
+    Ok(())
+}
+
+/// 宽限期内检测用户输入的间隔
+const GRACE_POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// 带宽限期的关闭屏幕指令
+///
+/// 先把亮度调暗到 `[display.dim] level` 并等待 `grace_secs`：
+/// 期间检测到本地用户输入时恢复亮度并取消熄屏，否则按正常流程关闭屏幕。
+/// 宽限期内收到的 `on` 指令会中断该任务，由 `on` 的处理恢复亮度。
+///
+/// # Arguments
+/// * `restore` - 调暗前的亮度，下次开启屏幕时恢复
+///
+/// # Returns
+/// * `Result<bool, String>` - 是否关闭了屏幕
+async fn run_dim_then_off(
+    backend: Arc<dyn screen::DisplayBackend>,
+    display_cfg: screen::DisplayConfig,
+    restore: Arc<Mutex<Option<u8>>>,
+) -> Result<bool, String> {
+    let dim = display_cfg.dim.clone();
+    let started = Instant::now();
+
+    let state = {
+        let backend = backend.clone();
+        tokio::task::spawn_blocking(move || screen::get_display_state(backend.as_ref()))
+            .await
+            .map_err(|e| format!("屏幕状态检测任务异常退出: {}", e))?
+    };
+    if state == screen::ScreenState::Off {
+        log_info("ℹ️ 屏幕已经处于关闭状态，无需操作");
+        return Ok(false);
+    }
+
+    log_info(&format!("🌙 进入熄屏宽限期 {} 秒", dim.grace_secs));
+    if backend.capabilities().brightness {
+        let current = {
+            let backend = backend.clone();
+            tokio::task::spawn_blocking(move || backend.brightness())
+                .await
+                .map_err(|e| format!("亮度读取任务异常退出: {}", e))?
+        };
+        match current {
+            Ok(level) => {
+                // 连续收到 off 时保留最初的亮度，而不是已经调暗后的亮度
+                restore.lock().unwrap().get_or_insert(level);
+                run_brightness_command(backend.clone(), None, dim.level, dim.fade_ms).await?;
+            }
+            Err(e) => log_warn(&format!("读取亮度失败，宽限期内不调暗: {}", e)),
+        }
+    }
+
+    let x_display = display_cfg.dpms.display.clone();
+    let input = tokio::task::spawn_blocking(move || idle::InputMonitor::new(x_display.as_deref()))
+        .await
+        .map_err(|e| format!("用户输入检测初始化异常退出: {}", e))?;
+    let deadline = started + Duration::from_secs(dim.grace_secs);
+    while Instant::now() < deadline {
+        tokio::time::sleep(GRACE_POLL_INTERVAL.min(deadline - Instant::now())).await;
+        if input.idle_time().is_some_and(|idle| idle < started.elapsed()) {
+            log_info("👋 宽限期内检测到用户操作，取消熄屏");
+            let level = restore.lock().unwrap().take();
+            if let Some(level) = level {
+                run_brightness_command(backend, None, level, dim.fade_ms).await?;
+            }
+            return Ok(false);
+        }
+    }
+
+    let changed = run_power_command(backend, display_cfg, None, false).await?;
+    if changed {
+        log_info("✅ 屏幕关闭操作完成");
+    } else {
+        log_info("ℹ️ 屏幕已经处于关闭状态，无需操作");
+    }
+    Ok(changed)
+}
+
+/// 执行显示器枚举指令，生成发布到回复主题的 JSON
+///
+/// 枚举过程可能涉及 DDC/CI 等阻塞读写，同样放到阻塞线程池中执行。
+///
+/// # Returns
+/// * `Value` - 包含显示器列表的回复，枚举失败时包含 `error` 字段
+async fn run_enumerate(backend: Arc<dyn screen::DisplayBackend>) -> Value {
+    let name = backend.name();
+    let result = tokio::task::spawn_blocking(move || screen::enumerate_displays(backend.as_ref()))
+        .await
+        .map_err(|e| format!("显示器枚举任务异常退出: {}", e))
+        .and_then(|result| result);
+    match result {
+        Ok(displays) => serde_json::json!({ "action": "enumerate", "backend": name, "displays": displays }),
+        Err(e) => serde_json::json!({ "action": "enumerate", "backend": name, "error": e }),
+    }
+}
+
+/// 连接失败时切换到列表中的下一个 Broker
+fn next_broker(index: &mut usize, brokers: &[mqtt::Broker]) {
+    if brokers.len() > 1 {
+        *index = (*index + 1) % brokers.len();
+    }
+}
+
 /// MQTT 监听和屏幕控制逻辑
 async fn run_mqtt_client(
     mut command_rx: mpsc::Receiver<MqttCommand>,
     status_tx: std_mpsc::Sender<MqttStatus>,
 ) {
     log_info("MQTT 客户端启动");
-    let mut retry_count = 0;
-    const MAX_RETRIES: u32 = 10; // 增加最大重试次数
-    const INITIAL_RETRY_DELAY: Duration = Duration::from_secs(1);
-    const MAX_RETRY_DELAY: Duration = Duration::from_secs(60);
-    let mut current_retry_delay = INITIAL_RETRY_DELAY;
+    // 连续连接失败的次数，按 [reconnect] 配置计算重连等待时间
+    let mut backoff = mqtt::Backoff::default();
     let mut mqtt_running = false;
     
     // 连接状态和统计信息
@@ -331,6 +1118,23 @@ async fn run_mqtt_client(
     let mut last_heartbeat = Instant::now();
     let heartbeat_interval = Duration::from_secs(30); // 30秒心跳间隔
 
+    // 当前使用的显示后端及其对应的配置，配置变化时重新选择
+    let mut display: Option<(screen::DisplayConfig, Arc<dyn screen::DisplayBackend>)> = None;
+    // 正在进行的亮度渐变、熄屏宽限期或延迟执行的开关指令任务
+    let mut pending_task: Option<PendingTask> = None;
+    // 当前的 MQTT 连接
+    let mut session: Option<MqttSession> = None;
+    // 当前使用的 Broker 在 Broker 列表中的序号，连接失败时切换到下一个
+    let mut broker_index = 0;
+    // 已使用的签名 nonce，重连后仍然保留，防止断线前的指令被重放
+    let mut nonces = auth::NonceCache::default();
+    // 按来源限制指令的保持期与冷却时间
+    let policy_state: Arc<Mutex<policy::PolicyState>> = Arc::new(Mutex::new(policy::PolicyState::default()));
+    // 最近一次开关屏幕的时间，用于开关指令防抖
+    let debouncer: Arc<Mutex<screen::Debouncer>> = Arc::new(Mutex::new(screen::Debouncer::default()));
+    // 熄屏前调暗时记录的原亮度，下次开启屏幕时恢复
+    let restore_brightness: Arc<Mutex<Option<u8>>> = Arc::new(Mutex::new(None));
+
     loop {
         tokio::select! {
             // 处理托盘命令
@@ -341,12 +1145,15 @@ async fn run_mqtt_client(
                             log_info("收到启动 MQTT 连接命令");
                             mqtt_running = true;
                             connection_state = ConnectionState::Connecting;
-                            retry_count = 0;
-                            current_retry_delay = INITIAL_RETRY_DELAY;
+                            backoff.reset();
+                            broker_index = 0;
                         }
                     }
                     Some(MqttCommand::Stop) => {
                         log_info("收到停止 MQTT 连接命令");
+                        if let Some(session) = session.take() {
+                            session.close().await;
+                        }
                         mqtt_running = false;
                         connection_state = ConnectionState::Disconnected;
                         if let ConnectionState::Connected = connection_state {
@@ -354,6 +1161,17 @@ async fn run_mqtt_client(
                         }
                         let _ = status_tx.send(MqttStatus::Stopped);
                     }
+                    Some(MqttCommand::Quit) => {
+                        log_info("收到退出命令，停止 MQTT 客户端");
+                        if let Some(session) = session.take() {
+                            session.close().await;
+                        }
+                        if let ConnectionState::Connected = connection_state {
+                            connection_stats.on_disconnection();
+                        }
+                        let _ = status_tx.send(MqttStatus::Stopped);
+                        break;
+                    }
                     None => {
                         log_info("命令通道关闭，停止 MQTT 客户端");
                         let _ = status_tx.send(MqttStatus::Stopped);
@@ -390,109 +1208,439 @@ async fn run_mqtt_client(
                     }
                 };
 
+                if display.as_ref().map(|(c, _)| c) != Some(&cfg.display) {
+                    match screen::create_backend(&cfg.display) {
+                        Ok(backend) => {
+                            log_info(&format!("🖥️ 使用显示后端: {}", backend.name()));
+                            display = Some((cfg.display.clone(), backend));
+                        }
+                        Err(e) => {
+                            let msg = format!("启动 MQTT 连接失败（显示后端不可用）：{}", e);
+                            log_error(&msg);
+                            connection_state = ConnectionState::Disconnected;
+                            mqtt_running = false;
+                            let _ = status_tx.send(MqttStatus::Error(msg));
+                            let _ = status_tx.send(MqttStatus::Stopped);
+                            return;
+                        }
+                    }
+                }
+                let backend = match &display {
+                    Some((_, backend)) => backend.clone(),
+                    None => return,
+                };
+
+                let brokers = cfg.brokers();
+                let broker = brokers[broker_index % brokers.len()].clone();
+                connection_stats.on_connection_start();
                 if connection_state == ConnectionState::Connecting {
-                    connection_stats.on_connection_start();
                     let _ = status_tx.send(MqttStatus::Started);
-                    let connect_msg = format!("正在连接到 MQTT Broker: {}:{}", cfg.broker_ip, cfg.broker_port);
+                    let connect_msg = format!("正在连接到 MQTT Broker: {}", broker.name());
                     log_info(&connect_msg);
                 }
+                connection_stats.set_active_broker(broker.name());
 
-                let mut options = MqttOptions::new("auto_screen_switch", cfg.broker_ip.clone(), cfg.broker_port);
-                options.set_keep_alive(Duration::from_secs(60)); // 增加保活时间
-                options.set_clean_session(true);
-                options.set_max_packet_size(100 * 1024, 100 * 1024); // 100KB 最大包大小
-                
-                if let (Some(u), Some(p)) = (cfg.username.clone(), cfg.password.clone()) {
-                    options.set_credentials(u, p);
+                let client_id = cfg.mqtt.client_id();
+                log_info(&format!("MQTT 客户端 ID: {}", client_id));
+                let transport = match mqtt::transport(broker.transport, &broker.tls) {
+                    Ok(transport) => transport,
+                    Err(e) => {
+                        let msg = format!("启动 MQTT 连接失败（TLS 配置错误）：{}", e);
+                        log_error(&msg);
+                        connection_state = ConnectionState::Disconnected;
+                        mqtt_running = false;
+                        let _ = status_tx.send(MqttStatus::Error(msg));
+                        let _ = status_tx.send(MqttStatus::Stopped);
+                        return;
+                    }
+                };
+                if broker.transport != mqtt::TransportKind::Tcp {
+                    log_info("🌐 使用 WebSocket 连接 MQTT");
+                }
+                if broker.transport.uses_tls(&broker.tls) {
+                    log_info("🔒 使用 TLS 连接 MQTT");
+                    if broker.tls.insecure_skip_verify {
+                        log_warn("⚠️ 已关闭 TLS 服务器证书校验，仅可用于实验环境");
+                    }
+                }
+                if cfg.mqtt.protocol == client::ProtocolVersion::V5 {
+                    log_info("使用 MQTT 5 协议");
+                }
+                if !cfg.mqtt.clean_session {
+                    log_info(&format!("使用持久会话 (指令 QoS: {})", cfg.mqtt.qos));
+                    if cfg.mqtt.qos == 0 {
+                        log_warn("⚠️ qos = 0 时 Broker 不会缓存断线期间的指令，建议使用 qos = 1");
+                    }
+                }
+
+                let credentials = if let (Some(u), Some(p)) = (broker.username.clone(), broker.password.clone()) {
                     log_info("使用认证信息连接 MQTT");
+                    Some((u, p))
                 } else {
                     log_info("使用匿名连接 MQTT");
+                    None
+                };
+
+                let auth_key = match cfg.auth.key() {
+                    Ok(key) => key,
+                    Err(e) => {
+                        let msg = format!("启动 MQTT 连接失败（签名密钥错误）：{}", e);
+                        log_error(&msg);
+                        connection_state = ConnectionState::Disconnected;
+                        mqtt_running = false;
+                        let _ = status_tx.send(MqttStatus::Error(msg));
+                        let _ = status_tx.send(MqttStatus::Stopped);
+                        return;
+                    }
+                };
+                if auth_key.is_some() {
+                    log_info("🔐 只接受带有效签名的指令");
                 }
+                nonces.set_capacity(cfg.auth.nonce_cache_size);
 
-                let (client, mut eventloop) = AsyncClient::new(options, 10);
+                let availability_topic = cfg.mqtt.availability_topic();
+                let options = client::ConnectOptions {
+                    client_id: client_id.clone(),
+                    // WebSocket 传输时 rumqttc 从 URL 中读取端口
+                    broker_addr: broker.addr(),
+                    port: broker.port,
+                    transport,
+                    keep_alive: Duration::from_secs(u64::from(cfg.mqtt.keep_alive_secs)),
+                    clean_session: cfg.mqtt.clean_session,
+                    session_expiry_secs: cfg.mqtt.session_expiry_secs,
+                    inflight: cfg.mqtt.inflight,
+                    max_packet_size: cfg.mqtt.max_packet_size,
+                    credentials,
+                    // 异常断开时由 Broker 发布离线状态
+                    last_will: (availability_topic.clone(), AVAILABILITY_OFFLINE.to_string()),
+                };
+
+                let (client, eventloop) = client::connect(cfg.mqtt.protocol, options, 10);
+                let MqttSession { client, eventloop, .. } = session.insert(MqttSession {
+                    client,
+                    eventloop,
+                    availability_topic: availability_topic.clone(),
+                });
                 
-                match client.subscribe("actuator/autoScreenSwitch", QoS::AtMostOnce).await {
+                let mut command_filters = cfg.mqtt.command_filters();
+                // Home Assistant 亮度指令主题，未被已有的指令主题覆盖时额外订阅
+                let ha_topics = if cfg.homeassistant.enabled {
+                    cfg.homeassistant.topics(&cfg.mqtt)
+                } else {
+                    None
+                };
+                let ha_brightness_topic = ha_topics.as_ref().map(|topics| topics.brightness.clone());
+                if let Some(topic) = &ha_brightness_topic {
+                    if !command_filters.iter().any(|filter| rumqttc::matches(topic, filter)) {
+                        command_filters.push(topic.clone());
+                    }
+                }
+                let response_topic = cfg.mqtt.response_topic();
+                let ack_topic = cfg.mqtt.ack_topic();
+                let state_publisher = StatePublisher::new(client.clone(), cfg.mqtt.state_topic());
+                let state_poll = (cfg.mqtt.state_poll_secs > 0).then(|| Duration::from_secs(cfg.mqtt.state_poll_secs));
+                // 连接失败或断开的原因，正常停止时为 None
+                let failure = match client.subscribe_many(&command_filters, cfg.mqtt.qos()).await {
                     Ok(_) => {
-                        log_info("✅ 主题订阅成功: actuator/autoScreenSwitch");
-                        connection_state = ConnectionState::Connected;
-                        connection_stats.on_connection_success();
-                        retry_count = 0;
-                        current_retry_delay = INITIAL_RETRY_DELAY;
-                        last_heartbeat = Instant::now();
+                        log_info(&format!("✅ 主题订阅成功: {}", command_filters.join(", ")));
+
+                        match client.publish(&availability_topic, QoS::AtLeastOnce, true, AVAILABILITY_ONLINE).await {
+                            Ok(_) => log_info(&format!("📡 已发布在线状态: {}", availability_topic)),
+                            Err(e) => log_warn(&format!("发布在线状态失败: {}", e)),
+                        }
+                        if let Some(topics) = &ha_topics {
+                            let messages = homeassistant::discovery_messages(
+                                &cfg.homeassistant,
+                                &cfg.mqtt,
+                                topics,
+                                &client_id,
+                                backend.name(),
+                                backend.capabilities(),
+                            );
+                            for (topic, payload) in messages {
+                                if let Err(e) = client.publish(&topic, QoS::AtLeastOnce, true, payload).await {
+                                    log_warn(&format!("发布 Home Assistant 自动发现配置失败 ({}): {}", topic, e));
+                                }
+                            }
+                            log_info("🏠 已发布 Home Assistant 自动发现配置");
+                        }
+                        state_publisher.publish(backend.clone(), "connect", false).await;
+                        let mut last_state_check = Instant::now();
                         
                         loop {
                             if !mqtt_running {
                                 log_info("停止 MQTT 监听");
                                 connection_state = ConnectionState::Disconnected;
                                 connection_stats.on_disconnection();
-                                break;
+                                break None;
+                            }
+
+                            // 定期检查屏幕状态，系统自行熄屏或有人手动开关屏幕时也能更新状态主题
+                            if state_poll.is_some_and(|interval| last_state_check.elapsed() >= interval) {
+                                last_state_check = Instant::now();
+                                state_publisher.publish(backend.clone(), "system", true).await;
                             }
 
                             match tokio::time::timeout(Duration::from_millis(500), eventloop.poll()).await {
-                                Ok(Ok(Event::Incoming(Incoming::Publish(p)))) => {
+                                Ok(Ok(client::Event::Connected { session_present })) => {
+                                    connection_state = ConnectionState::Connected;
+                                    connection_stats.on_connection_success();
+                                    if session_present {
+                                        log_info("📥 Broker 保留了上次的会话，将按顺序补发断线期间的指令");
+                                    }
+                                    backoff.reset();
+                                    last_heartbeat = Instant::now();
+                                }
+                                Ok(Ok(client::Event::Publish(p))) => {
                                     let payload_str = String::from_utf8_lossy(&p.payload);
-                                    let cmd_msg = format!("📨 收到控制指令: '{}'", payload_str);
+                                    let cmd_msg = format!("📨 收到控制指令: '{}' (主题: {})", payload_str, p.topic);
                                     log_info(&cmd_msg);
+
+                                    // 启用签名校验时拒绝未签名、签名无效或重放的指令，且不回复，避免被用来向任意主题发布消息
+                                    if let Some(key) = &auth_key {
+                                        if let Err(e) = auth::verify(&cfg.auth, key, &mut nonces, &p.payload, chrono::Utc::now()) {
+                                            log_warn(&format!("🔐 拒绝指令: {} (主题: {})", e, p.topic));
+                                            continue;
+                                        }
+                                    }
+
+                                    // 执行完成后回复结果：MQTT 5 的 Response Topic 优先，其次是默认确认主题；
+                                    // Response Topic 与 reply_to 一样只接受 [mqtt] reply_topics 或本节点前缀下的主题
+                                    let v5_response_topic = p.response_topic.clone().filter(|topic| {
+                                        let allowed = cfg.mqtt.reply_allowed(topic);
+                                        if !allowed {
+                                            log_warn(&format!("❌ Response Topic 不是允许的回复主题，使用默认确认主题: '{}'", topic));
+                                        }
+                                        allowed
+                                    });
+                                    let mut reply = v5_response_topic
+                                        .clone()
+                                        .or_else(|| ack_topic.clone())
+                                        .map(|topic| Reply::new(client.clone(), backend.clone(), topic, p.correlation_data.clone()));
                                     
-                                    // 解析 JSON 消息
-                                    match serde_json::from_slice::<MqttMessage>(&p.payload) {
-                                        Ok(msg) => {
-                                            let source = if let Some(params) = &msg.params {
-                                                params.get("source")
-                                                    .and_then(|s| s.as_str())
-                                                    .unwrap_or("unknown")
+                                    // 解析 JSON 消息（或 Home Assistant 的纯文本指令）
+                                    let mut msg = match parse_command(&p.topic, &p.payload, ha_brightness_topic.as_deref()) {
+                                        Ok(msg) => msg,
+                                        Err(e) => {
+                                            let error_msg = format!("❌ 指令解析失败: {} (原始消息: '{}')", e, payload_str);
+                                            log_error(&error_msg);
+                                            if let Some(reply) = reply {
+                                                reply.send(CommandResult::Error(e)).await;
+                                            }
+                                            continue;
+                                        }
+                                    };
+                                    // 指令中的 reply_to 优先于默认确认主题，但只接受 [mqtt] reply_topics 或本节点前缀下的主题
+                                    let mut reply_requested = v5_response_topic.is_some();
+                                    if let (None, Some(reply_to)) = (&v5_response_topic, &msg.reply_to) {
+                                        if cfg.mqtt.reply_allowed(reply_to) {
+                                            reply = Some(Reply::new(client.clone(), backend.clone(), reply_to.clone(), None));
+                                            reply_requested = true;
+                                        } else {
+                                            log_warn(&format!("❌ reply_to 不是允许的回复主题，使用默认确认主题: '{}'", reply_to));
+                                        }
+                                    }
+                                    if let Some(reply) = &mut reply {
+                                        reply.id = msg.id.clone().unwrap_or(Value::Null);
+                                        reply.action = Some(msg.action.clone());
+                                    }
+
+                                    // MQTT 5 的 User Property `source` 可以代替 params.source
+                                    if let Some(source) = p.user_property("source") {
+                                        let params = msg.params.get_or_insert_with(|| serde_json::json!({}));
+                                        if let Some(params) = params.as_object_mut() {
+                                            params.entry("source").or_insert_with(|| source.into());
+                                        }
+                                    }
+
+                                    let source = if let Some(params) = &msg.params {
+                                        params.get("source")
+                                            .and_then(|s| s.as_str())
+                                            .unwrap_or("unknown")
+                                    } else {
+                                        "unknown"
+                                    };
+
+                                    // 忽略过期的指令，避免断线期间积压的指令在很久之后才执行
+                                    if let Err(e) = timestamp::check_command_age(msg.params.as_ref(), msg.ts.as_ref(), &cfg.mqtt, chrono::Utc::now()) {
+                                        log_warn(&format!("⏰ 忽略指令 '{}': {} (来源: {})", msg.action, e, source));
+                                        if let Some(reply) = reply {
+                                            reply.send(CommandResult::Error(e)).await;
+                                        }
+                                        continue;
+                                    }
+                                    let admitted = policy_state.lock().unwrap().check(&cfg.policy, source, &msg.action, Instant::now());
+                                    if let Err(e) = admitted {
+                                        log_warn(&format!("🚫 拒绝指令 '{}': {}", msg.action, e));
+                                        if let Some(reply) = reply {
+                                            reply.send(CommandResult::Error(e)).await;
+                                        }
+                                        continue;
+                                    }
+                                    // 过期或被规则拒绝的指令不读取屏幕状态，确认消息中的 previous_state / new_state 为 null
+                                    if let Some(reply) = &mut reply {
+                                        reply.previous_state = Some(query_display_state(backend.clone()).await);
+                                    }
+
+                                    // 解析目标显示器（params.display），未指定时控制全部显示器
+                                    let selectors = match msg.params.as_ref().and_then(|p| p.get("display")) {
+                                        Some(value) => match screen::DisplaySelector::parse(value) {
+                                            Ok(selectors) => Some(selectors),
+                                            Err(e) => {
+                                                log_warn(&format!("❌ params.display 无效: {} (来源: {})", e, source));
+                                                if let Some(reply) = reply {
+                                                    reply.send(CommandResult::Error(format!("params.display 无效: {}", e))).await;
+                                                }
+                                                continue;
+                                            }
+                                        },
+                                        None => None,
+                                    };
+                                    let target = match &selectors {
+                                        Some(selectors) => format!("{:?}", selectors),
+                                        None => "全部".to_string(),
+                                    };
+
+                                    // 新的控制指令会中断正在进行的亮度渐变、熄屏宽限期或延迟执行的开关指令
+                                    if matches!(msg.action.as_str(), "on" | "off" | "brightness") {
+                                        if let Some(task) = pending_task.take() {
+                                            task.interrupt().await;
+                                        }
+                                    }
+                                    
+                                    // 后台任务与 enumerate 自行回复，其余指令在这里得到执行结果
+                                    let result = match msg.action.as_str() {
+                                        "on" | "off" => {
+                                            let on = msg.action == "on";
+                                            let log_msg = format!(
+                                                "执行操作: {}屏幕 (来源: {}, 显示器: {})",
+                                                if on { "开启" } else { "关闭" }, source, target
+                                            );
+                                            log_info(&log_msg);
+
+                                            let command = PowerCommand {
+                                                backend: backend.clone(),
+                                                display_cfg: cfg.display.clone(),
+                                                selectors: selectors.clone(),
+                                                on,
+                                                source: source.to_string(),
+                                                state_publisher: state_publisher.clone(),
+                                                status_tx: status_tx.clone(),
+                                                restore_brightness: restore_brightness.clone(),
+                                                debouncer: debouncer.clone(),
+                                                policy_cfg: cfg.policy.clone(),
+                                                policy_state: policy_state.clone(),
+                                            };
+                                            // 整体开关屏幕时按 [display.debounce] 合并抖动的指令，并保证最短开启/关闭时间
+                                            let delay = if selectors.is_none() {
+                                                debouncer.lock().unwrap().delay(&cfg.display.debounce, on, Instant::now())
                                             } else {
-                                                "unknown"
+                                                Duration::ZERO
                                             };
-                                            
-                                            match msg.action.as_str() {
-                                                "on" => {
-                                                    let log_msg = format!("执行操作: 开启屏幕 (来源: {})", source);
-                                                    log_info(&log_msg);
-                                                    
-                                                    // 使用智能屏幕控制，避免重复操作
-                                                    if screen::set_display_smart(true) {
-                                                        log_info("✅ 屏幕开启操作完成");
-                                                    } else {
-                                                        log_info("ℹ️ 屏幕已经处于开启状态，无需操作");
-                                                    }
+                                            if delay.is_zero() && !command.uses_grace() {
+                                                Some(command.run().await)
+                                            } else {
+                                                if !delay.is_zero() {
+                                                    log_info(&format!("⏳ 防抖：{:?} 后执行，期间收到新指令时取消", delay));
                                                 }
-                                                "off" => {
-                                                    let log_msg = format!("执行操作: 关闭屏幕 (来源: {})", source);
+                                                pending_task = Some(PendingTask::spawn(reply.take(), async move {
+                                                    tokio::time::sleep(delay).await;
+                                                    command.run().await
+                                                }));
+                                                None
+                                            }
+                                        }
+                                        "brightness" => {
+                                            let params = msg.params.as_ref();
+                                            match params.and_then(|p| p.get("level")).and_then(|l| l.as_u64()) {
+                                                Some(level) if level <= 100 => {
+                                                    let level = level as u8;
+                                                    let fade_ms = params
+                                                        .and_then(|p| p.get("fade_ms"))
+                                                        .and_then(|f| f.as_u64())
+                                                        .unwrap_or(0);
+
+                                                    let log_msg = format!(
+                                                        "执行操作: 调节亮度到 {}%，渐变 {} 毫秒 (来源: {}, 显示器: {})",
+                                                        level, fade_ms, source, target
+                                                    );
                                                     log_info(&log_msg);
-                                                    
-                                                    // 使用智能屏幕控制，避免重复操作
-                                                    if screen::set_display_smart(false) {
-                                                        log_info("✅ 屏幕关闭操作完成");
-                                                    } else {
-                                                        log_info("ℹ️ 屏幕已经处于关闭状态，无需操作");
-                                                    }
+
+                                                    // 手动调节亮度后不再恢复熄屏前的亮度
+                                                    restore_brightness.lock().unwrap().take();
+                                                    policy_state.lock().unwrap().record(&cfg.policy, source, Instant::now());
+
+                                                    let backend = backend.clone();
+                                                    let selectors = selectors.clone();
+                                                    let status_tx = status_tx.clone();
+                                                    let state_publisher = state_publisher.clone();
+                                                    let source = source.to_string();
+                                                    pending_task = Some(PendingTask::spawn(reply.take(), async move {
+                                                        match run_brightness_command(backend.clone(), selectors, level, fade_ms).await {
+                                                            Ok(()) => {
+                                                                log_info(&format!("✅ 亮度已调节到 {}%", level));
+                                                                state_publisher.publish(backend, &source, false).await;
+                                                                CommandResult::Executed
+                                                            }
+                                                            Err(e) => {
+                                                                let error_msg = format!("❌ 亮度调节失败: {}", e);
+                                                                log_error(&error_msg);
+                                                                let _ = status_tx.send(MqttStatus::Error(error_msg));
+                                                                CommandResult::Error(e)
+                                                            }
+                                                        }
+                                                    }));
+                                                    None
                                                 }
                                                 _ => {
-                                                    let unknown_msg = format!("❌ 收到未知指令: '{}' (来源: {})", msg.action, source);
-                                                    log_warn(&unknown_msg);
+                                                    log_warn(&format!("❌ brightness 指令缺少有效的 params.level (0-100) (来源: {})", source));
+                                                    Some(CommandResult::Error("缺少有效的 params.level (0-100)".to_string()))
                                                 }
                                             }
                                         }
-                                        Err(e) => {
-                                            let error_msg = format!("❌ JSON 解析失败: {} (原始消息: '{}')", e, payload_str);
-                                            log_error(&error_msg);
+                                        "enumerate" => {
+                                            let log_msg = format!("执行操作: 枚举显示器 (来源: {})", source);
+                                            log_info(&log_msg);
+
+                                            let displays = run_enumerate(backend.clone()).await;
+                                            let result = match displays.get("error").and_then(|e| e.as_str()) {
+                                                Some(e) => CommandResult::Error(e.to_string()),
+                                                None => CommandResult::Executed,
+                                            };
+                                            // 请求指定了回复主题时显示器列表随回复发布，否则发布到 response_topic
+                                            if reply_requested {
+                                                if let Some(reply) = reply.take() {
+                                                    reply.send_with(result, displays).await;
+                                                }
+                                                None
+                                            } else {
+                                                match client.publish(&response_topic, QoS::AtMostOnce, false, displays.to_string()).await {
+                                                    Ok(_) => log_info(&format!("✅ 显示器列表已发布到 {}", response_topic)),
+                                                    Err(e) => log_error(&format!("❌ 发布显示器列表失败: {}", e)),
+                                                }
+                                                Some(result)
+                                            }
                                         }
+                                        _ => {
+                                            let unknown_msg = format!("❌ 收到未知指令: '{}' (来源: {})", msg.action, source);
+                                            log_warn(&unknown_msg);
+                                            Some(CommandResult::Error(format!("未知指令: '{}'", msg.action)))
+                                        }
+                                    };
+                                    if let (Some(reply), Some(result)) = (reply, result) {
+                                        reply.send(result).await;
                                     }
                                 }
-                                Ok(Ok(Event::Incoming(Incoming::Disconnect))) => {
-                                    log_warn("⚠️ MQTT Broker 主动断开连接");
-                                    connection_state = ConnectionState::Disconnected;
-                                    connection_stats.on_disconnection();
-                                    break;
+                                Ok(Ok(client::Event::Disconnect)) => {
+                                    let error_msg = "MQTT Broker 主动断开连接".to_string();
+                                    log_warn(&format!("⚠️ {}", error_msg));
+                                    break Some(error_msg);
                                 }
                                 Ok(Ok(_)) => {} // 忽略其他 MQTT 事件
                                 Ok(Err(e)) => {
                                     let error_msg = format!("MQTT 连接错误: {}", e);
                                     log_error(&error_msg);
-                                    connection_state = ConnectionState::Disconnected;
-                                    connection_stats.on_disconnection();
-                                    break;
+                                    break Some(error_msg);
                                 }
                                 Err(_) => {} // 超时，继续循环
                             }
@@ -501,32 +1649,45 @@ async fn run_mqtt_client(
                     Err(e) => {
                         let error_msg = format!("MQTT 订阅失败: {}", e);
                         log_error(&error_msg);
-                        connection_state = ConnectionState::Disconnected;
-                        connection_stats.on_connection_failure();
-                        
-                        retry_count += 1;
-                        if retry_count >= MAX_RETRIES {
-                            log_error(&format!("达到最大重试次数 ({}), 停止重连", MAX_RETRIES));
-                            log_info(&connection_stats.get_uptime_stats());
-                            mqtt_running = false;
-                            let _ = status_tx.send(MqttStatus::Error(error_msg));
-                            let _ = status_tx.send(MqttStatus::Stopped);
-                        } else {
-                            // 指数退避重连策略
-                            current_retry_delay = std::cmp::min(
-                                current_retry_delay * 2,
-                                MAX_RETRY_DELAY
-                            );
-                            
-                            let retry_msg = format!(
-                                "第 {} 次重连失败，等待 {:?} 后重试... (最大重试次数: {})",
-                                retry_count, current_retry_delay, MAX_RETRIES
-                            );
-                            log_warn(&retry_msg);
-                            
-                            connection_state = ConnectionState::Reconnecting;
-                            tokio::time::sleep(current_retry_delay).await;
-                        }
+                        Some(error_msg)
+                    }
+                };
+
+                let Some(error_msg) = failure else {
+                    return;
+                };
+                // 连接建立前失败计为连接失败，建立后断开计为断线
+                if connection_state == ConnectionState::Connected {
+                    connection_stats.on_disconnection();
+                } else {
+                    connection_stats.on_connection_failure();
+                }
+                connection_state = ConnectionState::Disconnected;
+                session = None;
+                next_broker(&mut broker_index, &brokers);
+
+                match backoff.next_delay(&cfg.reconnect) {
+                    Some(delay) => {
+                        // 指数退避重连策略
+                        let max_retries = match cfg.reconnect.max_retries {
+                            0 => "不限".to_string(),
+                            n => n.to_string(),
+                        };
+                        let retry_msg = format!(
+                            "第 {} 次重连失败，等待 {:?} 后重试... (最大重试次数: {})",
+                            backoff.failures(), delay, max_retries
+                        );
+                        log_warn(&retry_msg);
+
+                        connection_state = ConnectionState::Reconnecting;
+                        tokio::time::sleep(delay).await;
+                    }
+                    None => {
+                        log_error(&format!("达到最大重试次数 ({}), 停止重连", cfg.reconnect.max_retries));
+                        log_info(&connection_stats.get_uptime_stats());
+                        mqtt_running = false;
+                        let _ = status_tx.send(MqttStatus::Error(error_msg));
+                        let _ = status_tx.send(MqttStatus::Stopped);
                     }
                 }
             } => {}
@@ -535,6 +1696,7 @@ async fn run_mqtt_client(
 }
 
 /// 程序主入口点
+#[cfg(windows)]
 fn main() {
     // 初始化日志记录器
     if let Err(e) = init_logger() {
@@ -594,7 +1756,7 @@ fn main() {
     
     // 启动 MQTT 客户端（创建 tokio 运行时）
     let runtime = tokio::runtime::Runtime::new().expect("无法创建Tokio运行时");
-    let mqtt_handle = runtime.spawn(run_mqtt_client(command_rx, status_tx.clone()));
+    let mut mqtt_handle = runtime.spawn(run_mqtt_client(command_rx, status_tx.clone()));
     
     // 默认启动 MQTT 连接（状态变化由后台任务回传）
     let _ = command_tx.blocking_send(MqttCommand::Start);
@@ -631,6 +1793,8 @@ fn main() {
                 }
             } else if event.id == quit_item.id() {
                 log_info("用户点击: 退出程序");
+                // 先让 MQTT 任务发布离线状态，事件循环结束后再等待它退出
+                let _ = command_tx.blocking_send(MqttCommand::Quit);
                 _target.exit();
             }
         }
@@ -654,7 +1818,107 @@ fn main() {
         }
     }).expect("事件循环运行失败");
 
-    // 停止 MQTT 客户端
+    // 等待 MQTT 任务发布离线状态，超时后强制停止
+    let _ = runtime.block_on(tokio::time::timeout(Duration::from_secs(3), &mut mqtt_handle));
+    mqtt_handle.abort();
+    log_info("👋 程序已退出");
+}
+/// 程序主入口点（无托盘模式）
+///
+/// 非 Windows 平台（如 Linux 信息屏）没有系统托盘，
+/// 启动后直接连接 MQTT，收到 SIGINT / SIGTERM 后退出。
+#[cfg(not(windows))]
+fn main() {
+    // 初始化日志记录器
+    if let Err(e) = init_logger() {
+        eprintln!("无法初始化日志记录器: {}", e);
+        std::process::exit(1);
+    }
+
+    let instance = SingleInstance::new("Global_AutoScreenSwitchMutex").expect("创建单实例句柄失败");
+    if !instance.is_single() {
+        log_warn("检测到已有实例在运行，当前进程将退出");
+        std::process::exit(0);
+    }
+
+    log_info("🚀 Auto Screen Switch 启动（无托盘模式）");
+
+    // 创建 MQTT 命令通道与状态通道
+    let (command_tx, command_rx) = mpsc::channel(10);
+    let (status_tx, status_rx) = std_mpsc::channel::<MqttStatus>();
+
+    let runtime = tokio::runtime::Runtime::new().expect("无法创建Tokio运行时");
+    let mut mqtt_handle = runtime.spawn(run_mqtt_client(command_rx, status_tx));
+
+    // 没有托盘按钮需要同步，只记录错误状态
+    std::thread::spawn(move || {
+        for status in status_rx {
+            if let MqttStatus::Error(msg) = status {
+                log_error(&format!("MQTT 状态错误: {}", msg));
+            }
+        }
+    });
+
+    let _ = command_tx.blocking_send(MqttCommand::Start);
+
+    runtime.block_on(async {
+        #[cfg(unix)]
+        {
+            use tokio::signal::unix::{signal, SignalKind};
+            let mut terminate = signal(SignalKind::terminate()).expect("无法注册 SIGTERM 处理");
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        #[cfg(not(unix))]
+        {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    });
+    log_info("收到退出信号");
+    let _ = command_tx.blocking_send(MqttCommand::Quit);
+
+    // 等待 MQTT 任务发布离线状态，超时后强制停止
+    let _ = runtime.block_on(tokio::time::timeout(Duration::from_secs(3), &mut mqtt_handle));
     mqtt_handle.abort();
     log_info("👋 程序已退出");
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_command_accepts_plain_on_off() {
+        for (payload, action) in [("ON", "on"), ("off", "off"), (" On\n", "on")] {
+            let msg = parse_command("actuator/autoScreenSwitch", payload.as_bytes(), None).unwrap();
+            assert_eq!(msg.action, action);
+            assert_eq!(msg.params, Some(serde_json::json!({ "source": "homeassistant" })));
+            assert!(msg.id.is_none() && msg.reply_to.is_none() && msg.ts.is_none());
+        }
+        // 其他纯文本不是有效指令
+        assert!(parse_command("actuator/autoScreenSwitch", b"toggle", None).is_err());
+    }
+
+    #[test]
+    fn parse_command_reads_json_and_brightness_topic() {
+        let brightness_topic = Some("actuator/autoScreenSwitch/brightness");
+        let msg = parse_command(
+            "actuator/autoScreenSwitch",
+            br#"{"action":"off","id":7,"params":{"source":"pir"}}"#,
+            brightness_topic,
+        )
+        .unwrap();
+        assert_eq!(msg.action, "off");
+        assert_eq!(msg.id, Some(serde_json::json!(7)));
+
+        let msg = parse_command("actuator/autoScreenSwitch/brightness", b"40", brightness_topic).unwrap();
+        assert_eq!(msg.action, "brightness");
+        assert_eq!(msg.params, Some(serde_json::json!({ "source": "homeassistant", "level": 40 })));
+        // 亮度主题上的 ON/OFF 不会被当作开关指令
+        assert!(parse_command("actuator/autoScreenSwitch/brightness", b"ON", brightness_topic).is_err());
+        // 未启用自动发现时亮度主题上的消息按普通指令解析
+        assert!(parse_command("actuator/autoScreenSwitch/brightness", b"40", None).is_err());
+    }
+}
@@ -0,0 +1,865 @@
+use rumqttc::tokio_rustls::rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use rumqttc::tokio_rustls::rustls::crypto::{self, ring, CryptoProvider};
+use rumqttc::tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime};
+use rumqttc::tokio_rustls::rustls::{self, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use rumqttc::{QoS, TlsConfiguration, Transport};
+use serde::Deserialize;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(windows)]
+use windows::core::PCWSTR;
+#[cfg(windows)]
+use windows::Win32::System::Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_SZ, RRF_SUBKEY_WOW6464KEY};
+
+/// MQTT 主题与客户端标识配置，对应 `config.toml` 中的 `[mqtt]` 段
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct MqttConfig {
+    /// 协议版本："3.1.1"（默认）或 "5"
+    pub protocol: crate::client::ProtocolVersion,
+    /// 客户端 ID 模板，支持 `{hostname}`、`{username}`、`{machine_id}` 占位符
+    pub client_id: String,
+    /// 本节点的主题前缀（如 "office/pc1"），为空时不加前缀
+    pub topic_prefix: String,
+    /// 订阅的指令主题，支持 `+` / `#` 通配符
+    pub command_topics: Vec<String>,
+    /// `enumerate` 等查询指令的回复主题
+    pub response_topic: String,
+    /// 屏幕状态主题（保留消息）
+    pub state_topic: String,
+    /// 定期检查屏幕状态的间隔（秒），状态变化时发布，为 0 时只在执行指令后发布
+    pub state_poll_secs: u64,
+    /// 在线状态主题：连接后发布 `online`，退出或异常断开（遗嘱消息）时为 `offline`
+    pub availability_topic: String,
+    /// 指令执行结果的默认确认主题，指令未指定 `reply_to` 时使用，为空（默认）时不发送
+    pub ack_topic: String,
+    /// 允许作为指令 `reply_to` 的主题，支持 `+` / `#` 通配符，不加 `topic_prefix`；
+    /// 为空时只允许 `topic_prefix` 下的主题
+    pub reply_topics: Vec<String>,
+    /// 订阅指令主题使用的 QoS（0、1 或 2）
+    pub qos: u8,
+    /// 每次连接是否新建会话；为 `false` 时 Broker 保留订阅，并缓存断线期间 QoS ≥ 1 的指令
+    pub clean_session: bool,
+    /// 持久会话在断开后保留的时间（秒），仅 MQTT 5 使用
+    pub session_expiry_secs: u32,
+    /// 保活间隔（秒，最大 65535），为 0 时不发送心跳
+    pub keep_alive_secs: u16,
+    /// 同时等待确认的消息数上限，MQTT 5 下同时作为 Receive Maximum
+    pub inflight: u16,
+    /// 收发消息的最大字节数
+    pub max_packet_size: usize,
+    /// 指令的最长有效时间（秒），根据 `params.ts` 判断，超过时忽略指令；为 0 时不检查
+    pub max_command_age_secs: u64,
+    /// 允许 `params.ts` 比本机时间超前的秒数，用于容忍时钟误差
+    pub max_clock_skew_secs: u64,
+}
+
+impl Default for MqttConfig {
+    fn default() -> Self {
+        Self {
+            protocol: crate::client::ProtocolVersion::default(),
+            client_id: "auto_screen_switch_{hostname}".to_string(),
+            topic_prefix: String::new(),
+            command_topics: vec!["actuator/autoScreenSwitch".to_string()],
+            response_topic: "actuator/autoScreenSwitch/response".to_string(),
+            state_topic: "actuator/autoScreenSwitch/state".to_string(),
+            state_poll_secs: 30,
+            availability_topic: "actuator/autoScreenSwitch/availability".to_string(),
+            ack_topic: String::new(),
+            reply_topics: Vec::new(),
+            qos: 0,
+            clean_session: true,
+            session_expiry_secs: 3600,
+            keep_alive_secs: 60,
+            inflight: 100,
+            max_packet_size: 100 * 1024,
+            max_command_age_secs: 0,
+            max_clock_skew_secs: 30,
+        }
+    }
+}
+
+impl MqttConfig {
+    /// 检查主题配置是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        if self.client_id.trim().is_empty() {
+            return Err("[mqtt] client_id 不能为空".to_string());
+        }
+        if self.command_topics.is_empty() {
+            return Err("[mqtt] command_topics 至少需要一个主题".to_string());
+        }
+        for filter in self.command_filters() {
+            if filter.is_empty() || !rumqttc::valid_filter(&filter) {
+                return Err(format!("[mqtt] 指令主题无效: '{}'", filter));
+            }
+        }
+        for filter in &self.reply_topics {
+            if filter.is_empty() || !rumqttc::valid_filter(filter) {
+                return Err(format!("[mqtt] reply_topics 中的主题无效: '{}'", filter));
+            }
+        }
+        if self.qos > 2 {
+            return Err(format!("[mqtt] qos 只能是 0、1 或 2: {}", self.qos));
+        }
+        if self.inflight == 0 {
+            return Err("[mqtt] inflight 必须大于 0".to_string());
+        }
+        if self.max_packet_size == 0 {
+            return Err("[mqtt] max_packet_size 必须大于 0".to_string());
+        }
+        let topics = [self.response_topic(), self.state_topic(), self.availability_topic()];
+        let command_filters = self.command_filters();
+        for topic in topics.into_iter().chain(self.ack_topic()) {
+            if topic.is_empty() || !rumqttc::valid_topic(&topic) {
+                return Err(format!("[mqtt] 发布主题无效（不能包含通配符）: '{}'", topic));
+            }
+            // 发布到指令主题的消息会被当作指令再次处理
+            if let Some(filter) = command_filters.iter().find(|filter| rumqttc::matches(&topic, filter)) {
+                return Err(format!("[mqtt] 发布主题 '{}' 与指令主题 '{}' 重叠", topic, filter));
+            }
+        }
+        Ok(())
+    }
+
+    /// 为主题加上本节点前缀
+    pub fn topic(&self, topic: &str) -> String {
+        let prefix = self.topic_prefix.trim_end_matches('/');
+        if prefix.is_empty() {
+            topic.to_string()
+        } else {
+            format!("{}/{}", prefix, topic)
+        }
+    }
+
+    /// 加上前缀后的指令订阅主题
+    pub fn command_filters(&self) -> Vec<String> {
+        self.command_topics.iter().map(|t| self.topic(t)).collect()
+    }
+
+    /// 加上前缀后的回复主题
+    pub fn response_topic(&self) -> String {
+        self.topic(&self.response_topic)
+    }
+
+    /// 加上前缀后的屏幕状态主题
+    pub fn state_topic(&self) -> String {
+        self.topic(&self.state_topic)
+    }
+
+    /// 加上前缀后的在线状态主题
+    pub fn availability_topic(&self) -> String {
+        self.topic(&self.availability_topic)
+    }
+
+    /// 加上前缀后的默认确认主题，未配置时为 `None`
+    pub fn ack_topic(&self) -> Option<String> {
+        (!self.ack_topic.is_empty()).then(|| self.topic(&self.ack_topic))
+    }
+
+    /// 指令中的 `reply_to` 是否可以作为回复主题
+    ///
+    /// 配置了 `reply_topics` 时必须匹配其中之一，否则必须位于 `topic_prefix` 之下，
+    /// 两者都未配置时不接受 `reply_to`，避免指令被用来向任意主题发布消息；
+    /// 与指令主题重叠的主题总是拒绝，否则回复会被当作指令再次处理。
+    pub fn reply_allowed(&self, topic: &str) -> bool {
+        if topic.is_empty() || !rumqttc::valid_topic(topic) {
+            return false;
+        }
+        if self.command_filters().iter().any(|filter| rumqttc::matches(topic, filter)) {
+            return false;
+        }
+        if !self.reply_topics.is_empty() {
+            return self.reply_topics.iter().any(|filter| rumqttc::matches(topic, filter));
+        }
+        let prefix = self.topic_prefix.trim_end_matches('/');
+        !prefix.is_empty() && topic.starts_with(&format!("{}/", prefix))
+    }
+
+    /// 订阅指令主题使用的 QoS
+    pub fn qos(&self) -> QoS {
+        rumqttc::qos(self.qos).unwrap_or(QoS::AtMostOnce)
+    }
+
+    /// 展开客户端 ID 模板中的占位符
+    pub fn client_id(&self) -> String {
+        let mut id = self.client_id.clone();
+        if id.contains("{hostname}") {
+            id = id.replace("{hostname}", &hostname());
+        }
+        if id.contains("{username}") {
+            id = id.replace("{username}", &username());
+        }
+        if id.contains("{machine_id}") {
+            id = id.replace("{machine_id}", &machine_id());
+        }
+        id
+    }
+}
+
+/// 本机主机名
+pub fn hostname() -> String {
+    gethostname::gethostname().to_string_lossy().into_owned()
+}
+
+/// 当前登录的系统用户名
+fn username() -> String {
+    std::env::var("USERNAME")
+        .or_else(|_| std::env::var("USER"))
+        .unwrap_or_else(|_| "unknown".to_string())
+}
+
+/// 本机的唯一标识（Windows 的 `MachineGuid`，Linux 的 `/etc/machine-id`）
+fn machine_id() -> String {
+    match read_machine_id() {
+        Some(id) => id,
+        None => {
+            crate::log_warn("无法读取本机 machine id，客户端 ID 中使用主机名代替");
+            hostname()
+        }
+    }
+}
+
+#[cfg(windows)]
+fn read_machine_id() -> Option<String> {
+    let key: Vec<u16> = "SOFTWARE\\Microsoft\\Cryptography\0".encode_utf16().collect();
+    let value: Vec<u16> = "MachineGuid\0".encode_utf16().collect();
+    let mut buf = [0u16; 64];
+    let mut size = std::mem::size_of_val(&buf) as u32;
+    unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            PCWSTR(key.as_ptr()),
+            PCWSTR(value.as_ptr()),
+            RRF_RT_REG_SZ | RRF_SUBKEY_WOW6464KEY,
+            None,
+            Some(buf.as_mut_ptr() as *mut _),
+            Some(&mut size),
+        )
+        .ok()
+        .ok()?;
+    }
+    let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    Some(String::from_utf16_lossy(&buf[..len]))
+}
+
+#[cfg(not(windows))]
+fn read_machine_id() -> Option<String> {
+    ["/etc/machine-id", "/var/lib/dbus/machine-id"]
+        .iter()
+        .find_map(|path| std::fs::read_to_string(path).ok())
+        .map(|id| id.trim().to_string())
+        .filter(|id| !id.is_empty())
+}
+
+/// MQTT 传输方式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TransportKind {
+    /// TCP，启用 `[tls]` 时为 TLS
+    #[default]
+    Tcp,
+    /// WebSocket
+    Ws,
+    /// 基于 TLS 的 WebSocket，使用 `[tls]` 中的证书配置
+    Wss,
+}
+
+impl TransportKind {
+    /// 是否使用 TLS 加密
+    pub fn uses_tls(self, tls: &TlsConfig) -> bool {
+        match self {
+            TransportKind::Tcp => tls.enabled,
+            TransportKind::Ws => false,
+            TransportKind::Wss => true,
+        }
+    }
+}
+
+/// 备用 Broker 配置，对应 `config.toml` 中的 `[[brokers]]`，未设置的字段沿用顶层配置
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct BrokerConfig {
+    /// Broker 的主机名或 IP 地址
+    pub host: String,
+    pub port: Option<u16>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub transport: Option<TransportKind>,
+    pub ws_path: Option<String>,
+    pub tls: Option<TlsConfig>,
+}
+
+/// 合并顶层配置后的一个 Broker 连接参数
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Broker {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub transport: TransportKind,
+    pub ws_path: String,
+    pub tls: TlsConfig,
+}
+
+impl Broker {
+    /// 用于日志与连接统计的名称：TCP 传输时为 `host:port`，WebSocket 传输时为 URL
+    pub fn name(&self) -> String {
+        match self.transport {
+            TransportKind::Tcp => format!("{}:{}", self.host, self.port),
+            _ => self.addr(),
+        }
+    }
+
+    /// 传给 rumqttc 的 Broker 地址
+    pub fn addr(&self) -> String {
+        broker_addr(self.transport, &self.host, self.port, &self.ws_path)
+    }
+
+    /// 检查连接参数是否有效（包括 TLS 证书能否加载）
+    pub fn validate(&self) -> Result<(), String> {
+        if self.host.is_empty() {
+            return Err("MQTT Broker IP 地址不能为空".to_string());
+        }
+        if self.port == 0 {
+            return Err(format!("MQTT Broker 端口号不能为 0 ({})", self.host));
+        }
+        if self.transport != TransportKind::Tcp {
+            validate_ws_path(&self.ws_path)?;
+        }
+        transport(self.transport, &self.tls).map(|_| ())
+    }
+}
+
+/// 检查 WebSocket 路径是否有效
+pub fn validate_ws_path(path: &str) -> Result<(), String> {
+    if !path.starts_with('/') || path.chars().any(|c| !c.is_ascii_graphic() || c == '#') {
+        return Err(format!("ws_path 无效（必须以 / 开头，且不能包含空白和 #）: '{}'", path));
+    }
+    Ok(())
+}
+
+/// Broker 地址：TCP 传输时为主机名，WebSocket 传输时为完整 URL
+pub fn broker_addr(kind: TransportKind, host: &str, port: u16, ws_path: &str) -> String {
+    let scheme = match kind {
+        TransportKind::Tcp => return host.to_string(),
+        TransportKind::Ws => "ws",
+        TransportKind::Wss => "wss",
+    };
+    // IPv6 地址在 URL 中需要加方括号
+    if host.contains(':') && !host.starts_with('[') {
+        format!("{}://[{}]:{}{}", scheme, host, port, ws_path)
+    } else {
+        format!("{}://{}:{}{}", scheme, host, port, ws_path)
+    }
+}
+
+/// 根据传输方式与 TLS 配置创建 rumqttc 传输层
+pub fn transport(kind: TransportKind, tls: &TlsConfig) -> Result<Transport, String> {
+    match kind {
+        TransportKind::Tcp if tls.enabled => Ok(Transport::tls_with_config(TlsConfiguration::Rustls(
+            tls.client_config()?,
+        ))),
+        TransportKind::Tcp => Ok(Transport::Tcp),
+        TransportKind::Ws if tls.enabled => {
+            Err("transport = \"ws\" 不加密，需要 TLS 时请使用 transport = \"wss\"".to_string())
+        }
+        TransportKind::Ws => Ok(Transport::Ws),
+        TransportKind::Wss => Ok(Transport::wss_with_config(TlsConfiguration::Rustls(tls.client_config()?))),
+    }
+}
+
+/// 重连策略，对应 `config.toml` 中的 `[reconnect]` 段
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(default)]
+pub struct ReconnectConfig {
+    /// 连续失败多少次后停止重连，为 0 时一直重连
+    pub max_retries: u32,
+    /// 第一次重连前的等待时间（秒），之后每次翻倍
+    pub initial_delay_secs: u64,
+    /// 重连等待时间的上限（秒）
+    pub max_delay_secs: u64,
+    /// 随机抖动比例（0-1），等待时间在 `±jitter` 范围内随机浮动（不超过 `max_delay_secs`），避免多个节点同时重连
+    pub jitter: f64,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            max_retries: 10,
+            initial_delay_secs: 1,
+            max_delay_secs: 60,
+            jitter: 0.0,
+        }
+    }
+}
+
+impl ReconnectConfig {
+    /// 检查重连策略是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        if self.initial_delay_secs == 0 {
+            return Err("[reconnect] initial_delay_secs 必须大于 0".to_string());
+        }
+        if self.max_delay_secs < self.initial_delay_secs {
+            return Err(format!(
+                "[reconnect] max_delay_secs ({}) 不能小于 initial_delay_secs ({})",
+                self.max_delay_secs, self.initial_delay_secs
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.jitter) {
+            return Err(format!("[reconnect] jitter 必须在 0 到 1 之间: {}", self.jitter));
+        }
+        Ok(())
+    }
+}
+
+/// 指数退避计时器，记录连续失败的次数
+#[derive(Debug, Default)]
+pub struct Backoff {
+    failures: u32,
+}
+
+impl Backoff {
+    /// 连接成功后清零失败次数
+    pub fn reset(&mut self) {
+        self.failures = 0;
+    }
+
+    /// 连续失败的次数
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// 记录一次失败并计算下次重连前的等待时间
+    ///
+    /// # Returns
+    /// * `Some(Duration)` - 下次重连前的等待时间
+    /// * `None` - 已达到最大重试次数，应停止重连
+    pub fn next_delay(&mut self, config: &ReconnectConfig) -> Option<Duration> {
+        self.failures = self.failures.saturating_add(1);
+        if config.max_retries > 0 && self.failures >= config.max_retries {
+            return None;
+        }
+        let factor = 2u64.saturating_pow(self.failures - 1);
+        let secs = config.initial_delay_secs.saturating_mul(factor).min(config.max_delay_secs);
+        let delay = Duration::from_secs(secs);
+        if config.jitter > 0.0 {
+            // 在 [1 - jitter, 1 + jitter] 内取随机系数，结果不超过最大等待时间
+            let factor = 1.0 + config.jitter * (2.0 * fastrand::f64() - 1.0);
+            Some(delay.mul_f64(factor).min(Duration::from_secs(config.max_delay_secs)))
+        } else {
+            Some(delay)
+        }
+    }
+}
+
+/// TLS 配置，对应 `config.toml` 中的 `[tls]` 段
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct TlsConfig {
+    /// 是否使用 TLS 连接 Broker（`transport = "wss"` 时总是使用 TLS）
+    pub enabled: bool,
+    /// CA 证书（PEM，可包含多个证书），未设置时使用系统根证书
+    pub ca_file: Option<PathBuf>,
+    /// 客户端证书链（PEM），与 `client_key` 一起配置时启用双向 TLS
+    pub client_cert: Option<PathBuf>,
+    /// 客户端私钥（PEM，支持 PKCS#8、PKCS#1 与 SEC1）
+    pub client_key: Option<PathBuf>,
+    /// ALPN 协议列表（如 `["mqtt"]`）
+    pub alpn: Vec<String>,
+    /// 跳过服务器证书校验，仅用于实验环境
+    pub insecure_skip_verify: bool,
+}
+
+impl TlsConfig {
+    /// 将相对路径解析为相对于配置文件所在目录的路径
+    pub fn resolve_paths(&mut self, base: &Path) {
+        for path in [&mut self.ca_file, &mut self.client_cert, &mut self.client_key].into_iter().flatten() {
+            if path.is_relative() {
+                *path = base.join(&*path);
+            }
+        }
+    }
+
+    /// 构建 rustls 客户端配置
+    pub fn client_config(&self) -> Result<Arc<ClientConfig>, String> {
+        let builder = ClientConfig::builder();
+        let builder = if self.insecure_skip_verify {
+            builder
+                .dangerous()
+                .with_custom_certificate_verifier(Arc::new(SkipServerVerification(ring::default_provider())))
+        } else {
+            builder.with_root_certificates(self.root_store()?)
+        };
+
+        let mut config = match (&self.client_cert, &self.client_key) {
+            (Some(cert), Some(key)) => builder
+                .with_client_auth_cert(load_certs(cert)?, load_private_key(key)?)
+                .map_err(|e| format!("客户端证书与私钥无效: {}", e))?,
+            (None, None) => builder.with_no_client_auth(),
+            _ => return Err("[tls] client_cert 与 client_key 必须同时配置".to_string()),
+        };
+        config.alpn_protocols = self.alpn.iter().map(|p| p.as_bytes().to_vec()).collect();
+        Ok(Arc::new(config))
+    }
+
+    /// 信任的根证书：配置的 CA 证书，未配置时使用系统根证书
+    fn root_store(&self) -> Result<RootCertStore, String> {
+        let mut store = RootCertStore::empty();
+        match &self.ca_file {
+            Some(path) => {
+                for cert in load_certs(path)? {
+                    store.add(cert).map_err(|e| format!("CA 证书 {:?} 无效: {}", path, e))?;
+                }
+            }
+            None => {
+                let certs = rustls_native_certs::load_native_certs()
+                    .map_err(|e| format!("无法加载系统根证书: {}", e))?;
+                store.add_parsable_certificates(certs);
+            }
+        }
+        if store.is_empty() {
+            return Err("没有可用的 CA 证书".to_string());
+        }
+        Ok(store)
+    }
+}
+
+/// 从 PEM 文件读取证书链
+fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>, String> {
+    let file = File::open(path).map_err(|e| format!("无法打开证书文件 {:?}: {}", path, e))?;
+    let certs = rustls_pemfile::certs(&mut BufReader::new(file))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("解析证书文件 {:?} 失败: {}", path, e))?;
+    if certs.is_empty() {
+        return Err(format!("证书文件 {:?} 中没有证书", path));
+    }
+    Ok(certs)
+}
+
+/// 从 PEM 文件读取私钥
+fn load_private_key(path: &Path) -> Result<PrivateKeyDer<'static>, String> {
+    let file = File::open(path).map_err(|e| format!("无法打开私钥文件 {:?}: {}", path, e))?;
+    rustls_pemfile::private_key(&mut BufReader::new(file))
+        .map_err(|e| format!("解析私钥文件 {:?} 失败: {}", path, e))?
+        .ok_or_else(|| format!("私钥文件 {:?} 中没有私钥", path))
+}
+
+/// 不校验服务器证书的验证器（`insecure_skip_verify`），握手签名仍正常校验
+#[derive(Debug)]
+struct SkipServerVerification(CryptoProvider);
+
+impl ServerCertVerifier for SkipServerVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        crypto::verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use rumqttc::tokio_rustls::rustls::server::WebPkiClientVerifier;
+    use rumqttc::tokio_rustls::rustls::ServerConfig;
+    use rumqttc::tokio_rustls::{TlsAcceptor, TlsConnector};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// 测试用的证书：CA、由 CA 签发的服务器证书（localhost / 127.0.0.1）与客户端证书，
+    /// 以及一个不相关的 CA
+    struct Pki {
+        dir: tempfile::TempDir,
+    }
+
+    impl Pki {
+        fn generate() -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            let write = |name: &str, content: String| std::fs::write(dir.path().join(name), content).unwrap();
+
+            let ca_key = KeyPair::generate().unwrap();
+            let mut ca_params = CertificateParams::new(Vec::new()).unwrap();
+            ca_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            let ca = ca_params.self_signed(&ca_key).unwrap();
+            write("ca.crt", ca.pem());
+
+            let server_key = KeyPair::generate().unwrap();
+            let server_params = CertificateParams::new(vec!["localhost".to_string(), "127.0.0.1".to_string()]).unwrap();
+            let server = server_params.signed_by(&server_key, &ca, &ca_key).unwrap();
+            write("server.crt", server.pem());
+            write("server.key", server_key.serialize_pem());
+
+            let client_key = KeyPair::generate().unwrap();
+            let client_params = CertificateParams::new(vec!["client".to_string()]).unwrap();
+            let client = client_params.signed_by(&client_key, &ca, &ca_key).unwrap();
+            write("client.crt", client.pem());
+            write("client.key", client_key.serialize_pem());
+
+            let other_key = KeyPair::generate().unwrap();
+            let mut other_params = CertificateParams::new(Vec::new()).unwrap();
+            other_params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+            write("other-ca.crt", other_params.self_signed(&other_key).unwrap().pem());
+
+            write("empty.crt", String::new());
+            Self { dir }
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.dir.path().join(name)
+        }
+
+        /// 在 127.0.0.1 上启动 TLS 服务端，握手成功后向客户端发送一个字节
+        async fn serve(&self, require_client_cert: bool) -> u16 {
+            let builder = ServerConfig::builder();
+            let builder = if require_client_cert {
+                let mut roots = RootCertStore::empty();
+                for cert in load_certs(&self.path("ca.crt")).unwrap() {
+                    roots.add(cert).unwrap();
+                }
+                builder.with_client_cert_verifier(WebPkiClientVerifier::builder(Arc::new(roots)).build().unwrap())
+            } else {
+                builder.with_no_client_auth()
+            };
+            let config = builder
+                .with_single_cert(
+                    load_certs(&self.path("server.crt")).unwrap(),
+                    load_private_key(&self.path("server.key")).unwrap(),
+                )
+                .unwrap();
+            let acceptor = TlsAcceptor::from(Arc::new(config));
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let port = listener.local_addr().unwrap().port();
+            tokio::spawn(async move {
+                while let Ok((stream, _)) = listener.accept().await {
+                    let acceptor = acceptor.clone();
+                    tokio::spawn(async move {
+                        if let Ok(mut stream) = acceptor.accept(stream).await {
+                            let _ = stream.write_all(b"k").await;
+                            let _ = stream.shutdown().await;
+                        }
+                    });
+                }
+            });
+            port
+        }
+
+        fn config(&self) -> TlsConfig {
+            TlsConfig {
+                enabled: true,
+                ca_file: Some(self.path("ca.crt")),
+                ..Default::default()
+            }
+        }
+
+        fn with_client_cert(&self) -> TlsConfig {
+            TlsConfig {
+                client_cert: Some(self.path("client.crt")),
+                client_key: Some(self.path("client.key")),
+                ..self.config()
+            }
+        }
+    }
+
+    /// 用给定配置连接服务端，握手完成并收到服务端数据时视为成功
+    async fn connect(config: &TlsConfig, port: u16, host: &str) -> Result<(), String> {
+        let connector = TlsConnector::from(config.client_config()?);
+        let stream = TcpStream::connect(("127.0.0.1", port)).await.map_err(|e| e.to_string())?;
+        let name = ServerName::try_from(host.to_string()).map_err(|e| e.to_string())?;
+        let mut stream = connector.connect(name, stream).await.map_err(|e| e.to_string())?;
+        let mut buf = [0u8; 1];
+        // TLS 1.3 中服务端拒绝客户端证书发生在客户端握手完成之后，读取数据时才会报错
+        stream.read_exact(&mut buf).await.map_err(|e| e.to_string())?;
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn ca_only_connection_succeeds() {
+        let pki = Pki::generate();
+        let port = pki.serve(false).await;
+        connect(&pki.config(), port, "localhost").await.unwrap();
+        connect(&pki.config(), port, "127.0.0.1").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn mutual_tls_succeeds_and_requires_client_cert() {
+        let pki = Pki::generate();
+        let port = pki.serve(true).await;
+        connect(&pki.with_client_cert(), port, "localhost").await.unwrap();
+        assert!(connect(&pki.config(), port, "localhost").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn insecure_skip_verify_accepts_untrusted_cert() {
+        let pki = Pki::generate();
+        let port = pki.serve(false).await;
+        let config = TlsConfig {
+            ca_file: Some(pki.path("other-ca.crt")),
+            insecure_skip_verify: true,
+            ..pki.config()
+        };
+        connect(&config, port, "localhost").await.unwrap();
+        connect(&config, port, "wrong.example.com").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn wrong_ca_or_hostname_is_rejected() {
+        let pki = Pki::generate();
+        let port = pki.serve(false).await;
+        let wrong_ca = TlsConfig {
+            ca_file: Some(pki.path("other-ca.crt")),
+            ..pki.config()
+        };
+        assert!(connect(&wrong_ca, port, "localhost").await.is_err());
+        assert!(connect(&pki.config(), port, "wrong.example.com").await.is_err());
+    }
+
+    #[test]
+    fn client_cert_without_key_is_rejected() {
+        let pki = Pki::generate();
+        let config = TlsConfig {
+            client_cert: Some(pki.path("client.crt")),
+            ..pki.config()
+        };
+        assert!(config.client_config().is_err());
+    }
+
+    #[test]
+    fn reply_to_must_be_under_prefix_by_default() {
+        let mut config = MqttConfig::default();
+        assert!(!config.reply_allowed("pi5/acks"));
+
+        config.topic_prefix = "office/pc1".to_string();
+        assert!(config.reply_allowed("office/pc1/acks"));
+        assert!(!config.reply_allowed("office/pc10/acks"));
+        assert!(!config.reply_allowed("pi5/acks"));
+        assert!(!config.reply_allowed("office/pc1/actuator/autoScreenSwitch"));
+        assert!(!config.reply_allowed("office/pc1/+"));
+    }
+
+    #[test]
+    fn reply_to_allowlist() {
+        let config = MqttConfig {
+            topic_prefix: "office/pc1".to_string(),
+            reply_topics: vec!["pi5/acks".to_string(), "controllers/+/acks".to_string()],
+            ..Default::default()
+        };
+        config.validate().unwrap();
+        assert!(config.reply_allowed("pi5/acks"));
+        assert!(config.reply_allowed("controllers/ha/acks"));
+        assert!(!config.reply_allowed("controllers/ha/other"));
+        assert!(!config.reply_allowed("office/pc1/acks"));
+
+        let invalid = MqttConfig {
+            reply_topics: vec!["a/#/b".to_string()],
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+
+    #[test]
+    fn publish_topics_must_not_match_command_topics() {
+        let config = MqttConfig {
+            command_topics: vec!["cmd/#".to_string()],
+            ack_topic: "cmd/ack".to_string(),
+            ..Default::default()
+        };
+        let err = config.validate().unwrap_err();
+        assert!(err.contains("'cmd/ack'") && err.contains("'cmd/#'"), "{}", err);
+
+        let config = MqttConfig {
+            topic_prefix: "office/pc1".to_string(),
+            command_topics: vec!["+/autoScreenSwitch/#".to_string()],
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+
+        let config = MqttConfig {
+            ack_topic: "actuator/autoScreenSwitch/ack".to_string(),
+            ..Default::default()
+        };
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn load_certs_reports_missing_file() {
+        let pki = Pki::generate();
+        let err = load_certs(&pki.path("missing.crt")).unwrap_err();
+        assert!(err.contains("无法打开证书文件"), "{}", err);
+    }
+
+    #[test]
+    fn load_certs_reports_empty_pem() {
+        let pki = Pki::generate();
+        let err = load_certs(&pki.path("empty.crt")).unwrap_err();
+        assert!(err.contains("中没有证书"), "{}", err);
+    }
+
+    #[test]
+    fn backoff_doubles_up_to_max_delay() {
+        let config = ReconnectConfig {
+            max_retries: 6,
+            initial_delay_secs: 2,
+            max_delay_secs: 10,
+            jitter: 0.0,
+        };
+        let mut backoff = Backoff::default();
+        let delays: Vec<_> = std::iter::from_fn(|| backoff.next_delay(&config)).map(|d| d.as_secs()).collect();
+        assert_eq!(delays, [2, 4, 8, 10, 10]);
+        assert_eq!(backoff.failures(), 6);
+
+        backoff.reset();
+        assert_eq!(backoff.next_delay(&config), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn backoff_jitter_stays_within_bounds() {
+        let config = ReconnectConfig {
+            max_retries: 0,
+            initial_delay_secs: 10,
+            max_delay_secs: 12,
+            jitter: 0.5,
+        };
+        let mut backoff = Backoff::default();
+        let mut delays = std::collections::HashSet::new();
+        for _ in 0..200 {
+            backoff.reset();
+            let delay = backoff.next_delay(&config).unwrap();
+            assert!((Duration::from_secs(5)..=Duration::from_secs(12)).contains(&delay), "{:?}", delay);
+            delays.insert(delay);
+        }
+        // 每次的等待时间都是随机的，超过 max_delay_secs 的部分被截断
+        assert!(delays.len() > 100, "{}", delays.len());
+        assert!(delays.contains(&Duration::from_secs(12)));
+    }
+}
@@ -0,0 +1,265 @@
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::time::{Duration, Instant};
+
+/// 可以在规则中使用的指令
+const ACTIONS: [&str; 4] = ["on", "off", "brightness", "enumerate"];
+
+/// 按来源（`params.source`）限制指令的配置，对应 `config.toml` 中的 `[policy]` 段
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct PolicyConfig {
+    /// 是否接受 `sources` 中未配置的来源，为 `false` 时 `sources` 即为来源白名单
+    pub allow_unknown_sources: bool,
+    /// 各来源的规则，键为来源名称（未带 `params.source` 的指令来源为 `unknown`）
+    pub sources: BTreeMap<String, SourceRule>,
+}
+
+impl Default for PolicyConfig {
+    fn default() -> Self {
+        Self {
+            allow_unknown_sources: true,
+            sources: BTreeMap::new(),
+        }
+    }
+}
+
+/// 一个来源的规则，对应 `[policy.sources.<来源>]`
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct SourceRule {
+    /// 允许执行的指令，为空时允许全部指令
+    pub actions: Vec<String>,
+    /// 优先级，数值越大越优先
+    pub priority: i32,
+    /// 执行控制指令后，优先级更低的来源在这段时间（秒）内不能控制屏幕
+    pub hold_secs: u64,
+    /// 同一来源两次控制指令之间的最短间隔（秒）
+    pub cooldown_secs: u64,
+}
+
+impl PolicyConfig {
+    /// 检查规则是否有效
+    pub fn validate(&self) -> Result<(), String> {
+        for (source, rule) in &self.sources {
+            if let Some(action) = rule.actions.iter().find(|action| !ACTIONS.contains(&action.as_str())) {
+                return Err(format!("[policy.sources.{}] 未知的指令: '{}'", source, action));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 是否为改变屏幕状态的控制指令，只有控制指令受优先级保持与冷却时间限制
+fn is_control(action: &str) -> bool {
+    matches!(action, "on" | "off" | "brightness")
+}
+
+/// 剩余时间向上取整到秒，用于提示还需等待多久
+fn ceil_secs(duration: Duration) -> u64 {
+    duration.as_secs() + u64::from(duration.subsec_nanos() > 0)
+}
+
+/// 高优先级来源设置的保持期
+#[derive(Debug)]
+struct Hold {
+    source: String,
+    priority: i32,
+    until: Instant,
+}
+
+/// 规则的运行状态：当前的保持期与各来源最后一次控制指令的时间
+#[derive(Debug, Default)]
+pub struct PolicyState {
+    hold: Option<Hold>,
+    last_command: HashMap<String, Instant>,
+}
+
+/// 未配置的来源使用的规则：允许全部指令，优先级为 0，没有保持期与冷却时间
+static DEFAULT_RULE: SourceRule = SourceRule {
+    actions: Vec::new(),
+    priority: 0,
+    hold_secs: 0,
+    cooldown_secs: 0,
+};
+
+/// 查找来源对应的规则
+///
+/// # Returns
+/// * `Err(String)` - 来源未配置且不接受未知来源
+fn rule_for<'a>(config: &'a PolicyConfig, source: &str) -> Result<&'a SourceRule, String> {
+    match config.sources.get(source) {
+        Some(rule) => Ok(rule),
+        None if config.allow_unknown_sources => Ok(&DEFAULT_RULE),
+        None => Err(format!("来源 '{}' 不在白名单中", source)),
+    }
+}
+
+impl PolicyState {
+    /// 按规则检查指令是否允许执行，不改变运行状态
+    ///
+    /// 通过检查的指令在真正执行时需要调用 [`PolicyState::record`]，
+    /// 参数无效或被新指令取消的指令不会开始保持期，也不计入冷却时间。
+    ///
+    /// # Arguments
+    /// * `config` - `[policy]` 配置
+    /// * `source` - 指令来源
+    /// * `action` - 指令名称
+    /// * `now` - 当前时间
+    ///
+    /// # Returns
+    /// * `Err(String)` - 指令被规则拒绝的原因
+    pub fn check(&self, config: &PolicyConfig, source: &str, action: &str, now: Instant) -> Result<(), String> {
+        let rule = rule_for(config, source)?;
+        if !rule.actions.is_empty() && !rule.actions.iter().any(|allowed| allowed == action) {
+            return Err(format!("来源 '{}' 不允许执行 '{}'", source, action));
+        }
+        if !is_control(action) {
+            return Ok(());
+        }
+
+        if let Some(hold) = &self.hold {
+            if now < hold.until && rule.priority < hold.priority {
+                return Err(format!(
+                    "来源 '{}' 的指令在保持期内，优先级更低的 '{}' 还需等待 {} 秒",
+                    hold.source,
+                    source,
+                    ceil_secs(hold.until.duration_since(now))
+                ));
+            }
+        }
+        if let Some(last) = self.last_command.get(source) {
+            let cooldown = Duration::from_secs(rule.cooldown_secs);
+            let elapsed = now.duration_since(*last);
+            if elapsed < cooldown {
+                return Err(format!(
+                    "来源 '{}' 的指令过于频繁，冷却时间还剩 {} 秒",
+                    source,
+                    ceil_secs(cooldown - elapsed)
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 记录开始执行的控制指令：开始冷却时间，并按来源的规则开始保持期
+    ///
+    /// # Arguments
+    /// * `config` - `[policy]` 配置
+    /// * `source` - 指令来源
+    /// * `now` - 指令开始执行的时间
+    pub fn record(&mut self, config: &PolicyConfig, source: &str, now: Instant) {
+        let Ok(rule) = rule_for(config, source) else {
+            return;
+        };
+        self.last_command.insert(source.to_string(), now);
+        // 同等或更高优先级的指令接管屏幕，结束之前的保持期
+        self.hold = (rule.hold_secs > 0).then(|| Hold {
+            source: source.to_string(),
+            priority: rule.priority,
+            until: now + Duration::from_secs(rule.hold_secs),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> PolicyConfig {
+        let config: PolicyConfig = toml::from_str(text).unwrap();
+        config.validate().unwrap();
+        config
+    }
+
+    /// 模拟主循环：检查通过后立即执行并记录
+    fn run(state: &mut PolicyState, config: &PolicyConfig, source: &str, action: &str, at: Instant) -> Result<(), String> {
+        state.check(config, source, action, at)?;
+        if is_control(action) {
+            state.record(config, source, at);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn allowlist_and_actions() {
+        let config = parse(
+            r#"
+            allow_unknown_sources = false
+            [sources.ha]
+            [sources.pir]
+            actions = ["on"]
+            "#,
+        );
+        let state = PolicyState::default();
+        let now = Instant::now();
+
+        assert!(state.check(&config, "ha", "off", now).is_ok());
+        assert!(state.check(&config, "pir", "on", now).is_ok());
+        let err = state.check(&config, "pir", "off", now).unwrap_err();
+        assert!(err.contains("不允许执行 'off'"), "{}", err);
+        let err = state.check(&config, "unknown", "on", now).unwrap_err();
+        assert!(err.contains("不在白名单中"), "{}", err);
+        assert!(state.check(&PolicyConfig::default(), "unknown", "on", now).is_ok());
+
+        let typo: PolicyConfig = toml::from_str("[sources.pir]\nactions = [\"reboot\"]").unwrap();
+        assert!(typo.validate().is_err());
+    }
+
+    #[test]
+    fn hold_and_cooldown() {
+        let config = parse(
+            r#"
+            [sources.manual]
+            priority = 10
+            hold_secs = 60
+            [sources.admin]
+            priority = 20
+            [sources.pir]
+            priority = 1
+            cooldown_secs = 10
+            "#,
+        );
+        let mut state = PolicyState::default();
+        let start = Instant::now();
+        let at = |secs: f64| start + Duration::from_secs_f64(secs);
+
+        run(&mut state, &config, "pir", "on", at(0.0)).unwrap();
+        let err = run(&mut state, &config, "pir", "off", at(5.0)).unwrap_err();
+        assert!(err.contains("冷却时间还剩 5 秒"), "{}", err);
+
+        run(&mut state, &config, "manual", "off", at(6.0)).unwrap();
+        let err = run(&mut state, &config, "pir", "on", at(35.5)).unwrap_err();
+        assert!(err.contains("还需等待 31 秒"), "{}", err);
+        // 查询指令不受保持期限制，未配置的来源优先级为 0 同样被拦截
+        run(&mut state, &config, "pir", "enumerate", at(36.0)).unwrap();
+        assert!(run(&mut state, &config, "other", "on", at(36.0)).is_err());
+
+        // 更高优先级且没有保持期的来源接管后，之前的保持期结束
+        run(&mut state, &config, "admin", "on", at(40.0)).unwrap();
+        run(&mut state, &config, "pir", "off", at(41.0)).unwrap();
+    }
+
+    #[test]
+    fn rejected_command_does_not_start_hold() {
+        let config = parse(
+            r#"
+            [sources.manual]
+            priority = 10
+            hold_secs = 60
+            cooldown_secs = 30
+            "#,
+        );
+        let mut state = PolicyState::default();
+        let now = Instant::now();
+
+        // 通过规则检查，但之后因 params 无效等原因没有执行，不调用 record
+        state.check(&config, "manual", "brightness", now).unwrap();
+        assert!(state.check(&config, "pir", "on", now).is_ok());
+        assert!(state.check(&config, "manual", "brightness", now).is_ok());
+
+        state.record(&config, "manual", now);
+        assert!(state.check(&config, "pir", "on", now).is_err());
+        assert!(state.check(&config, "manual", "on", now).is_err());
+    }
+}
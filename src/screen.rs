@@ -1,106 +1,934 @@
-use windows::Win32::Foundation::{LPARAM, WPARAM};
-use windows::Win32::UI::WindowsAndMessaging::{
-    SendMessageTimeoutW, HWND_BROADCAST, SC_MONITORPOWER, WM_SYSCOMMAND, SMTO_ABORTIFHUNG,
-};
-use std::sync::atomic::{AtomicBool, Ordering};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+#[cfg(windows)]
+mod win32;
+#[cfg(target_os = "linux")]
+mod dpms;
+#[cfg(target_os = "linux")]
+mod backlight;
+#[cfg(target_os = "linux")]
+mod ddc;
 
 /// 屏幕状态枚举
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum ScreenState {
     On,     // 屏幕开启
     Off,    // 屏幕关闭
     Unknown, // 状态未知
 }
 
-/// 全局屏幕状态跟踪器
-static SCREEN_STATE: AtomicBool = AtomicBool::new(true); // 默认认为屏幕是开启的
+/// 显示后端支持的能力
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Capabilities {
+    /// 是否支持开关显示器电源
+    pub power: bool,
+    /// 是否能够读回显示器的实际电源状态
+    pub query_state: bool,
+    /// 是否支持读取和调节亮度
+    pub brightness: bool,
+    /// 是否支持单独控制某一台显示器
+    pub per_display: bool,
+}
+
+/// 显示器分辨率（像素）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+/// 后端枚举出的单台显示器
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DisplayInfo {
+    /// 显示器序号（从 0 开始，按后端枚举顺序）
+    pub index: usize,
+    /// 显示器名称（EDID 型号名或设备名）
+    pub name: String,
+    /// EDID 序列号
+    pub serial: Option<String>,
+    /// 显示器所连接的接口（如 "DP-1"、"HDMI-A-1"、"DISPLAY2"）
+    pub connector: Option<String>,
+    /// 当前（或 EDID 推荐的）分辨率
+    pub resolution: Option<Resolution>,
+}
+
+/// MQTT 指令中 `params.display` 指定的目标显示器
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DisplaySelector {
+    /// 按序号选择
+    Index(usize),
+    /// 按名称、EDID 序列号或接口名选择（不区分大小写）
+    Text(String),
+}
+
+impl DisplaySelector {
+    /// 从 `params.display` 解析选择器，支持数字、字符串或两者组成的数组
+    pub fn parse(value: &Value) -> Result<Vec<Self>, String> {
+        match value {
+            Value::Array(items) => items.iter().map(Self::parse_one).collect(),
+            other => Ok(vec![Self::parse_one(other)?]),
+        }
+    }
+
+    fn parse_one(value: &Value) -> Result<Self, String> {
+        match value {
+            Value::Number(n) => n
+                .as_u64()
+                .map(|i| DisplaySelector::Index(i as usize))
+                .ok_or_else(|| format!("显示器序号无效: {}", n)),
+            Value::String(text) if !text.is_empty() => Ok(DisplaySelector::Text(text.clone())),
+            other => Err(format!("无法识别的显示器选择器: {}", other)),
+        }
+    }
+
+    /// 判断显示器是否被该选择器选中
+    pub fn matches(&self, info: &DisplayInfo) -> bool {
+        match self {
+            DisplaySelector::Index(index) => info.index == *index,
+            DisplaySelector::Text(text) => {
+                let eq = |field: &Option<String>| field.as_deref().is_some_and(|f| f.eq_ignore_ascii_case(text));
+                info.name.eq_ignore_ascii_case(text)
+                    || eq(&info.serial)
+                    || eq(&info.connector)
+                    || text.parse::<usize>().is_ok_and(|i| i == info.index)
+            }
+        }
+    }
+}
+
+/// 显示控制后端
+///
+/// 每种屏幕控制方式（Windows 广播、X11 DPMS 等）实现该 trait，
+/// 由 `create_backend` 根据配置在启动时选择具体实现。
+pub trait DisplayBackend: Send + Sync {
+    /// 后端名称，用于日志输出
+    fn name(&self) -> &'static str;
+
+    /// 后端支持的能力
+    fn capabilities(&self) -> Capabilities;
+
+    /// 开启或关闭显示器
+    ///
+    /// # Arguments
+    /// * `on` - `true` 表示开启屏幕，`false` 表示关闭屏幕
+    fn set_power(&self, on: bool) -> Result<(), String>;
+
+    /// 读取显示器当前的电源状态，无法获取时返回 `ScreenState::Unknown`
+    fn query_state(&self) -> ScreenState;
+
+    /// 读取当前亮度（0-100）
+    fn brightness(&self) -> Result<u8, String> {
+        Err(format!("显示后端 {} 不支持读取亮度", self.name()))
+    }
+
+    /// 设置亮度（0-100）
+    fn set_brightness(&self, _percent: u8) -> Result<(), String> {
+        Err(format!("显示后端 {} 不支持调节亮度", self.name()))
+    }
+
+    /// 枚举后端控制的显示器
+    fn displays(&self) -> Result<Vec<DisplayInfo>, String> {
+        Err(format!("显示后端 {} 不支持枚举显示器", self.name()))
+    }
+
+    /// 开启或关闭指定序号的显示器
+    ///
+    /// # Arguments
+    /// * `displays` - 目标显示器序号（来自 `displays()`）
+    /// * `on` - `true` 表示开启屏幕，`false` 表示关闭屏幕
+    fn set_power_for(&self, _displays: &[usize], _on: bool) -> Result<(), String> {
+        Err(format!("显示后端 {} 不支持单独控制显示器", self.name()))
+    }
+
+    /// 读取指定序号显示器的电源状态，无法获取时返回 `ScreenState::Unknown`
+    fn query_state_for(&self, _display: usize) -> ScreenState {
+        ScreenState::Unknown
+    }
+
+    /// 读取指定序号显示器的亮度（0-100）
+    fn brightness_for(&self, _display: usize) -> Result<u8, String> {
+        Err(format!("显示后端 {} 不支持单独读取显示器亮度", self.name()))
+    }
+
+    /// 设置指定序号显示器的亮度（0-100）
+    ///
+    /// # Arguments
+    /// * `displays` - 目标显示器序号（来自 `displays()`）
+    /// * `percent` - 目标亮度
+    fn set_brightness_for(&self, _displays: &[usize], _percent: u8) -> Result<(), String> {
+        Err(format!("显示后端 {} 不支持单独调节显示器亮度", self.name()))
+    }
+}
+
+/// 从 EDID 中读取文本描述符（0xFC 型号名，0xFF 序列号）
+#[cfg(any(windows, target_os = "linux"))]
+fn edid_descriptor_text(edid: &[u8], tag: u8) -> Option<String> {
+    edid.get(54..126)?.chunks(18).find_map(|d| {
+        if d[..3] == [0, 0, 0] && d[3] == tag {
+            // 文本以换行结束，其后用空格填充
+            let text = String::from_utf8_lossy(&d[5..18]);
+            Some(text.split('\n').next().unwrap_or("").trim().to_string())
+        } else {
+            None
+        }
+    })
+}
+
+/// 从 EDID 中读取序列号：优先使用文本描述符，否则使用基础块中的数字序列号
+#[cfg(any(windows, target_os = "linux"))]
+fn edid_serial(edid: &[u8]) -> Option<String> {
+    edid_descriptor_text(edid, 0xFF).filter(|s| !s.is_empty()).or_else(|| {
+        let serial = u32::from_le_bytes(edid.get(12..16)?.try_into().ok()?);
+        (serial != 0).then(|| serial.to_string())
+    })
+}
+
+/// 将 DRM 接口目录名（如 "card0-DP-1"）转换为接口名（"DP-1"）
+#[cfg(target_os = "linux")]
+fn drm_connector_name(dir_name: &str) -> String {
+    match dir_name.split_once('-') {
+        Some((card, connector)) if card.starts_with("card") => connector.to_string(),
+        _ => dir_name.to_string(),
+    }
+}
+
+/// 读取 DRM 接口目录下 `modes` 中的首个（当前推荐的）模式，如 "1920x1080"
+#[cfg(target_os = "linux")]
+fn drm_connector_resolution(connector_dir: &std::path::Path) -> Option<Resolution> {
+    let modes = std::fs::read_to_string(connector_dir.join("modes")).ok()?;
+    let (width, height) = modes.lines().next()?.split_once('x')?;
+    Some(Resolution {
+        width: width.parse().ok()?,
+        // 隔行模式以 "i" 结尾，如 "1920x1080i"
+        height: height.trim_end_matches(|c: char| !c.is_ascii_digit()).parse().ok()?,
+    })
+}
+
+/// 可选的显示后端类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackendKind {
+    /// 按当前平台自动选择
+    Auto,
+    /// Windows `SC_MONITORPOWER` 广播
+    Win32,
+    /// Linux X11 DPMS 扩展
+    Dpms,
+    /// Linux sysfs 背光（`/sys/class/backlight`）
+    Backlight,
+    /// DDC/CI（通过 `/dev/i2c-*` 直接控制显示器）
+    Ddc,
+}
+
+/// DPMS 电源等级
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DpmsLevel {
+    On,
+    Standby,
+    Suspend,
+    Off,
+}
+
+/// X11 DPMS 后端配置，对应 `[display.dpms]` 段
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct DpmsConfig {
+    /// X11 显示名称（如 ":0"），未设置时使用 `DISPLAY` 环境变量
+    pub display: Option<String>,
+    /// 关闭屏幕时使用的电源等级
+    pub off_level: DpmsLevel,
+}
+
+impl Default for DpmsConfig {
+    fn default() -> Self {
+        Self {
+            display: None,
+            off_level: DpmsLevel::Off,
+        }
+    }
+}
+
+/// sysfs 背光后端配置，对应 `[display.backlight]` 段
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct BacklightConfig {
+    /// 背光设备所在目录
+    pub sysfs_root: PathBuf,
+    /// 只控制这些设备（目录名），为空时控制全部设备
+    pub devices: Vec<String>,
+}
+
+impl Default for BacklightConfig {
+    fn default() -> Self {
+        Self {
+            sysfs_root: PathBuf::from("/sys/class/backlight"),
+            devices: Vec::new(),
+        }
+    }
+}
+
+/// DDC/CI 关闭显示器时使用的电源模式（VCP 0xD6）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DdcPowerMode {
+    Standby,
+    Suspend,
+    Off,
+    /// 断开电源，部分显示器此后无法再通过 DDC/CI 唤醒
+    HardOff,
+}
+
+impl DdcPowerMode {
+    /// 对应的 VCP 0xD6 取值
+    pub fn vcp_value(self) -> u16 {
+        match self {
+            DdcPowerMode::Standby => 2,
+            DdcPowerMode::Suspend => 3,
+            DdcPowerMode::Off => 4,
+            DdcPowerMode::HardOff => 5,
+        }
+    }
+}
+
+/// DDC/CI 后端配置，对应 `[display.ddc]` 段
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct DdcConfig {
+    /// 使用的 I2C 总线（如 "/dev/i2c-4"），为空时扫描全部总线
+    pub buses: Vec<PathBuf>,
+    /// 关闭屏幕时使用的电源模式
+    pub off_mode: DdcPowerMode,
+    /// 开启屏幕后切换到的输入源（VCP 0x60 取值，如 0x0F 为 DP1、0x11 为 HDMI1）
+    pub on_input: Option<u16>,
+}
+
+impl Default for DdcConfig {
+    fn default() -> Self {
+        Self {
+            buses: Vec::new(),
+            off_mode: DdcPowerMode::Off,
+            on_input: None,
+        }
+    }
+}
+
+/// 熄屏前调暗配置，对应 `[display.dim]` 段
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct DimConfig {
+    /// 收到 `off` 指令后的宽限期（秒），为 0 时立即熄屏
+    pub grace_secs: u64,
+    /// 宽限期内的亮度（0-100），后端不支持调节亮度时只等待不调暗
+    pub level: u8,
+    /// 调暗与恢复亮度的渐变时长（毫秒）
+    pub fade_ms: u64,
+}
+
+impl Default for DimConfig {
+    fn default() -> Self {
+        Self {
+            grace_secs: 0,
+            level: 10,
+            fade_ms: 1000,
+        }
+    }
+}
+
+/// 开关屏幕的防抖配置，对应 `config.toml` 中的 `[display.debounce]` 段
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct DebounceConfig {
+    /// 合并窗口（毫秒）：开关指令延迟这段时间执行，窗口内收到新指令时只执行最后一条
+    pub coalesce_ms: u64,
+    /// 开启屏幕后至少保持开启的时间（秒），期间收到的 `off` 延迟到期满后执行
+    pub min_on_secs: u64,
+    /// 关闭屏幕后至少保持关闭的时间（秒），期间收到的 `on` 延迟到期满后执行
+    pub min_off_secs: u64,
+}
+
+/// 记录最近一次开关屏幕的时间，计算开关指令需要延迟执行的时间
+#[derive(Debug, Default)]
+pub struct Debouncer {
+    last_change: Option<(bool, Instant)>,
+}
+
+impl Debouncer {
+    /// 记录一次开关屏幕操作
+    pub fn record(&mut self, on: bool, now: Instant) {
+        self.last_change = Some((on, now));
+    }
+
+    /// 开关指令需要延迟执行的时间：合并窗口与最短开启/关闭时间的剩余部分中较长的一个
+    ///
+    /// # Arguments
+    /// * `on` - 指令的目标状态
+    /// * `now` - 收到指令的时间
+    pub fn delay(&self, config: &DebounceConfig, on: bool, now: Instant) -> Duration {
+        let coalesce = Duration::from_millis(config.coalesce_ms);
+        let remaining = match self.last_change {
+            Some((last_on, changed_at)) if last_on != on => {
+                let min = if last_on { config.min_on_secs } else { config.min_off_secs };
+                (changed_at + Duration::from_secs(min)).saturating_duration_since(now)
+            }
+            _ => Duration::ZERO,
+        };
+        coalesce.max(remaining)
+    }
+}
+
+/// 显示控制配置，对应 `config.toml` 中的 `[display]` 段
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(default)]
+pub struct DisplayConfig {
+    /// 使用的显示后端
+    pub backend: BackendKind,
+    /// 执行开关操作后等待屏幕状态稳定的时间（毫秒），之后读回状态确认操作是否生效
+    pub settle_ms: u64,
+    /// 屏幕状态未改变时的重试次数
+    pub retries: u32,
+    /// 熄屏前调暗配置
+    pub dim: DimConfig,
+    /// 开关屏幕的防抖配置
+    pub debounce: DebounceConfig,
+    /// X11 DPMS 后端配置
+    pub dpms: DpmsConfig,
+    /// sysfs 背光后端配置
+    pub backlight: BacklightConfig,
+    /// DDC/CI 后端配置
+    pub ddc: DdcConfig,
+}
+
+impl Default for DisplayConfig {
+    fn default() -> Self {
+        Self {
+            backend: BackendKind::Auto,
+            settle_ms: 1000,
+            retries: 2,
+            dim: DimConfig::default(),
+            debounce: DebounceConfig::default(),
+            dpms: DpmsConfig::default(),
+            backlight: BacklightConfig::default(),
+            ddc: DdcConfig::default(),
+        }
+    }
+}
+
+/// 根据配置创建显示后端
+///
+/// # Arguments
+/// * `config` - `[display]` 配置段
+///
+/// # Returns
+/// * `Result<Arc<dyn DisplayBackend>, String>` - 创建成功的后端，或当前平台不支持时的错误信息
+pub fn create_backend(config: &DisplayConfig) -> Result<Arc<dyn DisplayBackend>, String> {
+    match config.backend {
+        #[cfg(windows)]
+        BackendKind::Auto | BackendKind::Win32 => Ok(Arc::new(win32::Win32Backend::new())),
+        #[cfg(not(windows))]
+        BackendKind::Win32 => Err("win32 显示后端仅支持 Windows".to_string()),
+
+        #[cfg(target_os = "linux")]
+        BackendKind::Dpms => Ok(Arc::new(dpms::DpmsBackend::new(&config.dpms)?)),
+        #[cfg(target_os = "linux")]
+        BackendKind::Backlight => Ok(Arc::new(backlight::BacklightBackend::new(&config.backlight)?)),
+        #[cfg(target_os = "linux")]
+        BackendKind::Ddc => Ok(Arc::new(ddc::DdcBackend::new(&config.ddc)?)),
+        #[cfg(target_os = "linux")]
+        BackendKind::Auto => {
+            // 优先直接控制面板背光，没有背光设备时再通过 X Server 控制
+            match backlight::BacklightBackend::new(&config.backlight) {
+                Ok(backend) => Ok(Arc::new(backend)),
+                Err(e) => {
+                    crate::log_info(&format!("背光后端不可用，改用 DPMS: {}", e));
+                    Ok(Arc::new(dpms::DpmsBackend::new(&config.dpms)?))
+                }
+            }
+        }
+        #[cfg(not(target_os = "linux"))]
+        BackendKind::Dpms | BackendKind::Backlight | BackendKind::Ddc => {
+            Err("dpms / backlight / ddc 显示后端仅支持 Linux".to_string())
+        }
+
+        #[cfg(not(any(windows, target_os = "linux")))]
+        BackendKind::Auto => Err("当前平台没有可用的显示后端".to_string()),
+    }
+}
+
+const SHADOW_UNKNOWN: u8 = 0;
+const SHADOW_ON: u8 = 1;
+const SHADOW_OFF: u8 = 2;
+
+/// 全局屏幕状态跟踪器，记录最后一次成功操作的结果
+///
+/// 仅在后端无法读回实际状态时使用；启动后尚未操作过时为未知。
+static SCREEN_STATE: AtomicU8 = AtomicU8::new(SHADOW_UNKNOWN);
+
+/// 读取内部状态跟踪记录的屏幕状态
+fn shadow_state() -> ScreenState {
+    match SCREEN_STATE.load(Ordering::Relaxed) {
+        SHADOW_ON => ScreenState::On,
+        SHADOW_OFF => ScreenState::Off,
+        _ => ScreenState::Unknown,
+    }
+}
 
 /// 检测当前屏幕状态
-/// 
-/// 该函数通过内存状态跟踪来检测屏幕状态：
-/// 由于 Windows API 检测屏幕状态比较复杂且不可靠，
-/// 我们使用内部状态跟踪来记录最后一次操作的结果。
-/// 
+///
+/// 优先查询后端读回的实际状态（DPMS 电源等级、背光 `bl_power`、DDC 0xD6 等），
+/// 因此系统自行熄屏后也能得到正确结果；
+/// 后端不支持查询或无法判断时，才使用内部状态跟踪记录的最后一次操作结果，
+/// 两者都无法确定时返回 `ScreenState::Unknown`。
+///
+/// # Arguments
+/// * `backend` - 当前使用的显示后端
+///
 /// # Returns
 /// * `ScreenState` - 当前屏幕状态
-pub fn get_display_state() -> ScreenState {
-    let current_state = SCREEN_STATE.load(Ordering::Relaxed);
-    if current_state {
-        ScreenState::On
-    } else {
-        ScreenState::Off
+pub fn get_display_state(backend: &dyn DisplayBackend) -> ScreenState {
+    if backend.capabilities().query_state {
+        match backend.query_state() {
+            ScreenState::Unknown => {
+                crate::log_warn(&format!("显示后端 {} 无法读取屏幕状态，使用内部状态跟踪", backend.name()));
+            }
+            state => return state,
+        }
     }
+
+    shadow_state()
 }
 
 /// 智能屏幕控制函数
-/// 
+///
 /// 该函数会先检测当前屏幕状态，避免重复操作：
 /// - 如果当前屏幕已开启且收到开启指令，则不执行操作
 /// - 如果当前屏幕已关闭且收到关闭指令，则不执行操作
-/// 
+/// - 如果当前屏幕状态未知，则总是执行操作
+///
 /// # Arguments
+/// * `backend` - 当前使用的显示后端
 /// * `target_state` - 目标屏幕状态：`true` 表示开启屏幕，`false` 表示关闭屏幕
-/// 
+/// * `config` - 显示控制配置（状态确认延时与重试次数）
+///
 /// # Returns
-/// * `bool` - 是否执行了操作：`true` 表示执行了操作，`false` 表示无需操作
-pub fn set_display_smart(target_state: bool) -> bool {
-    let current_state = get_display_state();
+/// * `Result<bool, String>` - 是否执行了操作：`true` 表示执行了操作，`false` 表示无需操作；
+///   执行后屏幕状态仍未改变时返回错误
+pub fn set_display_smart(
+    backend: &dyn DisplayBackend,
+    target_state: bool,
+    config: &DisplayConfig,
+) -> Result<bool, String> {
+    let current_state = get_display_state(backend);
     let target_screen_state = if target_state { ScreenState::On } else { ScreenState::Off };
-    
+
     // 检查是否需要执行操作
     match (current_state, target_screen_state) {
         (ScreenState::On, ScreenState::On) => {
             // 屏幕已经开启，无需重复操作
-            false
+            Ok(false)
         }
         (ScreenState::Off, ScreenState::Off) => {
             // 屏幕已经关闭，无需重复操作
-            false
+            Ok(false)
         }
         _ => {
             // 需要执行操作
-            set_display(target_state);
-            true
+            set_display(backend, target_state, config)?;
+            Ok(true)
+        }
+    }
+}
+
+/// `enumerate` 指令返回的单台显示器信息
+#[derive(Debug, Clone, Serialize)]
+pub struct DisplayReport {
+    /// 显示器序号，可作为 `params.display` 使用
+    pub id: usize,
+    pub name: String,
+    pub serial: Option<String>,
+    pub connector: Option<String>,
+    pub resolution: Option<Resolution>,
+    /// 当前电源状态
+    pub power: ScreenState,
+    /// 支持的控制方式（"power"、"brightness"、"per_display"）
+    pub controls: Vec<&'static str>,
+}
+
+/// 枚举后端控制的显示器及其当前状态
+///
+/// 支持单独控制的后端逐台读取电源状态，否则所有显示器共用整体状态。
+///
+/// # Arguments
+/// * `backend` - 当前使用的显示后端
+///
+/// # Returns
+/// * `Result<Vec<DisplayReport>, String>` - 显示器列表，或后端无法枚举时的错误信息
+pub fn enumerate_displays(backend: &dyn DisplayBackend) -> Result<Vec<DisplayReport>, String> {
+    let caps = backend.capabilities();
+    let controls: Vec<&'static str> = [
+        (caps.power, "power"),
+        (caps.brightness, "brightness"),
+        (caps.per_display, "per_display"),
+    ]
+    .into_iter()
+    .filter_map(|(supported, name)| supported.then_some(name))
+    .collect();
+
+    let displays = backend.displays()?;
+    let shared_state = (!caps.per_display).then(|| get_display_state(backend));
+    Ok(displays
+        .into_iter()
+        .map(|info| DisplayReport {
+            power: shared_state.clone().unwrap_or_else(|| backend.query_state_for(info.index)),
+            id: info.index,
+            name: info.name,
+            serial: info.serial,
+            connector: info.connector,
+            resolution: info.resolution,
+            controls: controls.clone(),
+        })
+        .collect())
+}
+
+/// 按显示器选择器解析出目标显示器序号
+///
+/// # Arguments
+/// * `backend` - 当前使用的显示后端，需要支持单独控制显示器
+/// * `selectors` - `params.display` 中的选择器
+///
+/// # Returns
+/// * `Result<Vec<usize>, String>` - 被选中的显示器序号，没有任何显示器匹配时返回错误
+pub fn resolve_displays(backend: &dyn DisplayBackend, selectors: &[DisplaySelector]) -> Result<Vec<usize>, String> {
+    if !backend.capabilities().per_display {
+        return Err(format!("显示后端 {} 不支持单独控制显示器", backend.name()));
+    }
+
+    let displays = backend.displays()?;
+    let selected: Vec<usize> = displays
+        .iter()
+        .filter(|info| selectors.iter().any(|s| s.matches(info)))
+        .map(|info| info.index)
+        .collect();
+    if selected.is_empty() {
+        return Err(format!("没有显示器匹配 {:?}", selectors));
+    }
+    Ok(selected)
+}
+
+/// 多台显示器亮度的平均值（四舍五入），跳过读取失败的显示器
+///
+/// # Arguments
+/// * `readings` - 各台显示器的亮度读数（0-100）
+///
+/// # Returns
+/// * `Err(String)` - 没有任何显示器读取成功时返回第一个错误
+pub fn mean_brightness(readings: impl IntoIterator<Item = Result<u8, String>>) -> Result<u8, String> {
+    let mut first_error = None;
+    let mut sum = 0u32;
+    let mut count = 0u32;
+    for reading in readings {
+        match reading {
+            Ok(percent) => {
+                sum += u32::from(percent);
+                count += 1;
+            }
+            Err(e) => {
+                first_error.get_or_insert(e);
+            }
+        }
+    }
+    if count == 0 {
+        return Err(first_error.unwrap_or_else(|| "没有可读取亮度的显示器".to_string()));
+    }
+    Ok(((sum + count / 2) / count) as u8)
+}
+
+/// 按显示器执行的智能屏幕控制
+///
+/// 与 `set_display_smart` 相同，但只操作指定的显示器：
+/// 跳过已处于目标状态的显示器，并逐台确认状态，未切换的显示器最多重试 `retries` 次。
+///
+/// # Arguments
+/// * `backend` - 当前使用的显示后端
+/// * `displays` - 目标显示器序号（来自 `resolve_displays`）
+/// * `target_state` - 目标屏幕状态：`true` 表示开启屏幕，`false` 表示关闭屏幕
+/// * `config` - 显示控制配置（状态确认延时与重试次数）
+///
+/// # Returns
+/// * `Result<bool, String>` - 是否执行了操作，或重试后仍有显示器未切换时的错误信息
+pub fn set_displays_smart(
+    backend: &dyn DisplayBackend,
+    displays: &[usize],
+    target_state: bool,
+    config: &DisplayConfig,
+) -> Result<bool, String> {
+    let target = if target_state { ScreenState::On } else { ScreenState::Off };
+    let mut pending: Vec<usize> = displays
+        .iter()
+        .copied()
+        .filter(|&i| backend.query_state_for(i) != target)
+        .collect();
+    if pending.is_empty() {
+        return Ok(false);
+    }
+
+    let attempts = config.retries + 1;
+    let mut last_error = String::new();
+    for attempt in 1..=attempts {
+        if let Err(e) = backend.set_power_for(&pending, target_state) {
+            last_error = format!("显示后端 {} 执行失败: {}", backend.name(), e);
+        } else {
+            thread::sleep(Duration::from_millis(config.settle_ms));
+            // 无法读回状态的显示器按操作成功处理
+            pending.retain(|&i| {
+                let state = backend.query_state_for(i);
+                state != ScreenState::Unknown && state != target
+            });
+            if pending.is_empty() {
+                return Ok(true);
+            }
+            last_error = format!("显示器 {:?} 状态仍未切换到 {:?} (后端: {})", pending, target, backend.name());
         }
+        crate::log_warn(&format!("第 {}/{} 次屏幕控制未生效: {}", attempt, attempts, last_error));
     }
+
+    Err(last_error)
 }
 
 /// 控制显示器电源状态
-/// 
-/// 该函数通过 Windows API 向所有窗口广播显示器电源控制消息，
-/// 实现屏幕的开启和关闭功能。
-/// 
+///
+/// 该函数调用显示后端执行屏幕的开启和关闭；
+/// 后端能够读回状态时，等待 `settle_ms` 后确认屏幕确实切换到目标状态，
+/// 未切换时最多重试 `retries` 次。成功后更新内部状态跟踪。
+///
 /// # Arguments
+/// * `backend` - 当前使用的显示后端
 /// * `on` - 显示器状态：`true` 表示开启屏幕，`false` 表示关闭屏幕
-/// 
-/// # Safety
-/// 此函数包含 unsafe 代码块，因为调用了 Windows API。
-/// 在 Windows 系统上调用是安全的。
-pub fn set_display(on: bool) {
-    unsafe {
-        // 根据开启/关闭状态设置显示器电源参数
-        // -1: 显示器开启
-        // 2: 显示器关闭
-        let state = if on { -1 } else { 2 };
-        // 使用 SendMessageTimeoutW 防止 HWND_BROADCAST 导致阻塞
-        // 设置较短的超时（例如 500ms），并在窗口挂起时中止
-        let mut _unused: usize = 0;
-        let _ = SendMessageTimeoutW(
-            HWND_BROADCAST,
-            WM_SYSCOMMAND,
-            WPARAM(SC_MONITORPOWER as usize),
-            LPARAM(state),
-            SMTO_ABORTIFHUNG,
-            500,
-            Some(&mut _unused as *mut usize),
+/// * `config` - 显示控制配置（状态确认延时与重试次数）
+///
+/// # Returns
+/// * `Result<(), String>` - 所有尝试都失败时返回最后一次的错误信息
+pub fn set_display(backend: &dyn DisplayBackend, on: bool, config: &DisplayConfig) -> Result<(), String> {
+    let attempts = config.retries + 1;
+    let mut last_error = String::new();
+
+    for attempt in 1..=attempts {
+        match apply_once(backend, on, config) {
+            Ok(()) => {
+                // 更新内部状态跟踪
+                SCREEN_STATE.store(if on { SHADOW_ON } else { SHADOW_OFF }, Ordering::Relaxed);
+                return Ok(());
+            }
+            Err(e) => {
+                crate::log_warn(&format!("第 {}/{} 次屏幕控制未生效: {}", attempt, attempts, e));
+                last_error = e;
+            }
+        }
+    }
+
+    Err(last_error)
+}
+
+/// 执行一次开关操作，并在后端支持时读回状态确认结果
+fn apply_once(backend: &dyn DisplayBackend, on: bool, config: &DisplayConfig) -> Result<(), String> {
+    backend
+        .set_power(on)
+        .map_err(|e| format!("显示后端 {} 执行失败: {}", backend.name(), e))?;
+
+    if !backend.capabilities().query_state {
+        // 无法读回状态，只能认为操作已生效
+        return Ok(());
+    }
+
+    thread::sleep(Duration::from_millis(config.settle_ms));
+    let target = if on { ScreenState::On } else { ScreenState::Off };
+    match backend.query_state() {
+        ScreenState::Unknown => {
+            // 部分显示器关闭后不再应答查询，无法确认时按成功处理
+            crate::log_warn(&format!("显示后端 {} 无法确认屏幕状态，按操作成功处理", backend.name()));
+            Ok(())
+        }
+        state if state == target => Ok(()),
+        state => Err(format!(
+            "屏幕状态仍为 {:?} (目标: {:?}, 后端: {})",
+            state,
+            target,
+            backend.name()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::atomic::AtomicU32;
+    use std::sync::Mutex;
+
+    #[test]
+    fn display_selector_parse() {
+        assert_eq!(DisplaySelector::parse(&json!(1)).unwrap(), [DisplaySelector::Index(1)]);
+        assert_eq!(
+            DisplaySelector::parse(&json!([0, "DP-1"])).unwrap(),
+            [DisplaySelector::Index(0), DisplaySelector::Text("DP-1".to_string())]
         );
-        
-        // 更新内部状态跟踪
-        SCREEN_STATE.store(on, Ordering::Relaxed);
-        
-        // 操作结果已在调用方记录日志
-        
-        // 注意：SendMessageW 的返回值在此上下文中通常不需要检查
-        // 因为显示器电源控制是一个广播消息，没有特定的返回值含义
+        for invalid in [json!(-1), json!(1.5), json!(""), json!(null), json!({"index": 0}), json!([0, true])] {
+            assert!(DisplaySelector::parse(&invalid).is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn display_selector_matches_name_serial_connector_or_index() {
+        let displays = [
+            DisplayInfo {
+                index: 0,
+                name: "DELL U2720Q".to_string(),
+                serial: Some("7X1Y2Z3".to_string()),
+                connector: Some("DP-1".to_string()),
+                resolution: None,
+            },
+            DisplayInfo {
+                index: 1,
+                name: "Built-in".to_string(),
+                serial: None,
+                connector: None,
+                resolution: None,
+            },
+        ];
+        let selected = |value: Value| -> Vec<usize> {
+            let selectors = DisplaySelector::parse(&value).unwrap();
+            displays
+                .iter()
+                .filter(|info| selectors.iter().any(|s| s.matches(info)))
+                .map(|info| info.index)
+                .collect()
+        };
+
+        assert_eq!(selected(json!("dell u2720q")), [0]);
+        assert_eq!(selected(json!("7x1y2z3")), [0]);
+        assert_eq!(selected(json!("dp-1")), [0]);
+        // 字符串形式的序号同样可以选中
+        assert_eq!(selected(json!("1")), [1]);
+        assert_eq!(selected(json!([1, "DP-1"])), [0, 1]);
+        // 名称需要完整匹配，没有序列号或接口名的显示器不会被误选
+        assert!(selected(json!("DELL")).is_empty());
+        assert!(selected(json!(2)).is_empty());
+    }
+
+    /// 前 `ignored` 次开关操作不生效的显示后端，用于检查读回确认与重试
+    struct StubbornBackend {
+        query_state: bool,
+        state: Mutex<ScreenState>,
+        ignored: AtomicU32,
+        calls: AtomicU32,
+    }
+
+    impl StubbornBackend {
+        fn new(query_state: bool, ignored: u32) -> Self {
+            Self {
+                query_state,
+                state: Mutex::new(ScreenState::On),
+                ignored: ignored.into(),
+                calls: 0.into(),
+            }
+        }
+    }
+
+    impl DisplayBackend for StubbornBackend {
+        fn name(&self) -> &'static str {
+            "stubborn"
+        }
+
+        fn capabilities(&self) -> Capabilities {
+            Capabilities {
+                power: true,
+                query_state: self.query_state,
+                ..Default::default()
+            }
+        }
+
+        fn set_power(&self, on: bool) -> Result<(), String> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            let ignored = self.ignored.load(Ordering::Relaxed);
+            if ignored > 0 {
+                self.ignored.store(ignored - 1, Ordering::Relaxed);
+            } else {
+                *self.state.lock().unwrap() = if on { ScreenState::On } else { ScreenState::Off };
+            }
+            Ok(())
+        }
+
+        fn query_state(&self) -> ScreenState {
+            self.state.lock().unwrap().clone()
+        }
+    }
+
+    #[test]
+    fn set_display_retries_until_state_changes() {
+        let config = DisplayConfig {
+            settle_ms: 0,
+            retries: 2,
+            ..Default::default()
+        };
+
+        let backend = StubbornBackend::new(true, 2);
+        set_display(&backend, false, &config).unwrap();
+        assert_eq!(backend.calls.load(Ordering::Relaxed), 3);
+        assert_eq!(backend.query_state(), ScreenState::Off);
+
+        let backend = StubbornBackend::new(true, 3);
+        let err = set_display(&backend, false, &config).unwrap_err();
+        assert!(err.contains("屏幕状态仍为 On"), "{}", err);
+        assert_eq!(backend.calls.load(Ordering::Relaxed), 3);
+
+        // 无法读回状态的后端只执行一次
+        let backend = StubbornBackend::new(false, 3);
+        set_display(&backend, false, &config).unwrap();
+        assert_eq!(backend.calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn debounce_disabled_by_default() {
+        let mut debouncer = Debouncer::default();
+        let now = Instant::now();
+        assert_eq!(debouncer.delay(&DebounceConfig::default(), false, now), Duration::ZERO);
+        debouncer.record(true, now);
+        assert_eq!(debouncer.delay(&DebounceConfig::default(), false, now), Duration::ZERO);
+    }
+
+    /// 人体传感器抖动：屏幕开启后很快收到 off，随后又收到 on
+    #[test]
+    fn flapping_sensor_is_held_by_min_times() {
+        let config = DebounceConfig {
+            coalesce_ms: 200,
+            min_on_secs: 60,
+            min_off_secs: 30,
+        };
+        let mut debouncer = Debouncer::default();
+        let start = Instant::now();
+        let at = |millis: u64| start + Duration::from_millis(millis);
+
+        // 没有开关记录时只有合并窗口
+        assert_eq!(debouncer.delay(&config, true, at(0)), Duration::from_millis(200));
+        debouncer.record(true, at(200));
+
+        // 开启 20 秒后的 off 要等满 min_on_secs，期间的 on 与当前状态相同，不受限制
+        assert_eq!(debouncer.delay(&config, false, at(20_200)), Duration::from_secs(40));
+        assert_eq!(debouncer.delay(&config, true, at(20_200)), Duration::from_millis(200));
+        // 剩余时间短于合并窗口时按合并窗口延迟
+        assert_eq!(debouncer.delay(&config, false, at(60_100)), Duration::from_millis(200));
+        debouncer.record(false, at(60_200));
+
+        // 熄屏后 min_off_secs 只影响 on，min_on_secs 不再起作用
+        assert_eq!(debouncer.delay(&config, true, at(70_200)), Duration::from_secs(20));
+        assert_eq!(debouncer.delay(&config, false, at(70_200)), Duration::from_millis(200));
+        assert_eq!(debouncer.delay(&config, true, at(90_200)), Duration::from_millis(200));
     }
 }
@@ -0,0 +1,434 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use super::{drm_connector_name, drm_connector_resolution, BacklightConfig, Capabilities, DisplayBackend, DisplayInfo, Resolution, ScreenState};
+
+/// `bl_power` 取值：背光开启（FB_BLANK_UNBLANK）
+const BL_POWER_ON: u32 = 0;
+/// `bl_power` 取值：背光关闭（FB_BLANK_POWERDOWN）
+const BL_POWER_OFF: u32 = 4;
+
+/// 单个背光设备，对应 `<sysfs_root>/<name>` 目录
+pub struct BacklightDevice {
+    name: String,
+    path: PathBuf,
+    /// 没有 `bl_power` 时通过亮度置零关闭，记录关闭前的亮度以便恢复
+    saved_brightness: Mutex<Option<u32>>,
+}
+
+impl BacklightDevice {
+    fn new(name: String, path: PathBuf) -> Self {
+        Self {
+            name,
+            path,
+            saved_brightness: Mutex::new(None),
+        }
+    }
+
+    /// 设备名称（sysfs 目录名）
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 背光所属的显示接口（`device` 链接指向 DRM 接口时才能得到，如 "eDP-1"）
+    pub fn connector(&self) -> Option<String> {
+        let target = fs::read_link(self.path.join("device")).ok()?;
+        let dir_name = target.file_name()?.to_string_lossy();
+        dir_name.starts_with("card").then(|| drm_connector_name(&dir_name))
+    }
+
+    /// 背光所属显示接口的当前分辨率
+    pub fn resolution(&self) -> Option<Resolution> {
+        drm_connector_resolution(&self.path.join("device"))
+    }
+
+    fn read_u32(&self, file: &str) -> Result<u32, String> {
+        let path = self.path.join(file);
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("读取 {:?} 失败: {}", path, e))?;
+        content
+            .trim()
+            .parse()
+            .map_err(|e| format!("解析 {:?} 失败: {}", path, e))
+    }
+
+    fn write_u32(&self, file: &str, value: u32) -> Result<(), String> {
+        let path = self.path.join(file);
+        fs::write(&path, value.to_string()).map_err(|e| format!("写入 {:?} 失败: {}", path, e))
+    }
+
+    fn has_bl_power(&self) -> bool {
+        self.path.join("bl_power").exists()
+    }
+
+    /// 最大亮度原始值
+    pub fn max_brightness(&self) -> Result<u32, String> {
+        self.read_u32("max_brightness")
+    }
+
+    /// 当前亮度原始值，优先读取硬件实际亮度 `actual_brightness`
+    pub fn brightness(&self) -> Result<u32, String> {
+        self.read_u32("actual_brightness")
+            .or_else(|_| self.read_u32("brightness"))
+    }
+
+    /// 当前亮度占最大亮度的百分比（0-100）
+    pub fn brightness_percent(&self) -> Result<u8, String> {
+        let max = self.max_brightness()?;
+        if max == 0 {
+            return Err(format!("背光设备 {} 的 max_brightness 为 0", self.name));
+        }
+        let percent = (self.brightness()? as u64 * 100 + max as u64 / 2) / max as u64;
+        Ok(percent.min(100) as u8)
+    }
+
+    /// 设置亮度原始值，超出范围时截断到 `max_brightness`
+    pub fn set_brightness(&self, value: u32) -> Result<(), String> {
+        let max = self.max_brightness()?;
+        self.write_u32("brightness", value.min(max))
+    }
+
+    /// 按最大亮度的百分比设置亮度
+    pub fn set_brightness_percent(&self, percent: u8) -> Result<(), String> {
+        let max = self.max_brightness()? as u64;
+        self.set_brightness(((max * percent.min(100) as u64 + 50) / 100) as u32)
+    }
+
+    /// 开启或关闭背光
+    pub fn set_power(&self, on: bool) -> Result<(), String> {
+        if self.has_bl_power() {
+            return self.write_u32("bl_power", if on { BL_POWER_ON } else { BL_POWER_OFF });
+        }
+
+        let mut saved = self.saved_brightness.lock().unwrap();
+        if on {
+            let restore = match saved.take() {
+                Some(value) if value > 0 => value,
+                _ => self.max_brightness()?,
+            };
+            self.set_brightness(restore)
+        } else {
+            let current = self.brightness()?;
+            if current > 0 {
+                *saved = Some(current);
+            }
+            self.write_u32("brightness", 0)
+        }
+    }
+
+    /// 读取背光实际状态：`bl_power` 非 0 或亮度为 0 时认为已关闭
+    pub fn is_on(&self) -> Result<bool, String> {
+        if self.has_bl_power() && self.read_u32("bl_power")? != BL_POWER_ON {
+            return Ok(false);
+        }
+        Ok(self.brightness()? > 0)
+    }
+}
+
+/// 在 sysfs 根目录下查找背光设备
+///
+/// # Arguments
+/// * `root` - 背光设备所在目录，通常为 `/sys/class/backlight`
+/// * `names` - 只使用这些名称的设备，为空时使用全部设备
+///
+/// # Returns
+/// * `Result<Vec<BacklightDevice>, String>` - 按名称排序的设备列表
+pub fn discover(root: &Path, names: &[String]) -> Result<Vec<BacklightDevice>, String> {
+    let entries = fs::read_dir(root).map_err(|e| format!("无法读取背光目录 {:?}: {}", root, e))?;
+
+    let mut devices = Vec::new();
+    for entry in entries.flatten() {
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !names.is_empty() && !names.contains(&name) {
+            continue;
+        }
+        // sysfs 中的设备目录是符号链接，使用 join 后的路径而不是 DirEntry 的文件类型判断
+        let path = root.join(&name);
+        if path.join("brightness").is_file() && path.join("max_brightness").is_file() {
+            devices.push(BacklightDevice::new(name, path));
+        }
+    }
+    devices.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(devices)
+}
+
+/// Linux sysfs 背光后端
+///
+/// 直接读写 `/sys/class/backlight/*/bl_power` 与 `brightness`，
+/// 不依赖显示服务器，适用于笔记本和嵌入式面板。
+pub struct BacklightBackend {
+    devices: Vec<BacklightDevice>,
+}
+
+impl BacklightBackend {
+    /// 查找并打开配置中的背光设备
+    ///
+    /// # Arguments
+    /// * `config` - `[display.backlight]` 配置段
+    pub fn new(config: &BacklightConfig) -> Result<Self, String> {
+        let devices = discover(&config.sysfs_root, &config.devices)?;
+        if devices.is_empty() {
+            return Err(format!("在 {:?} 下未找到可用的背光设备", config.sysfs_root));
+        }
+        let names: Vec<&str> = devices.iter().map(|d| d.name()).collect();
+        crate::log_info(&format!("发现背光设备: {}", names.join(", ")));
+        Ok(Self { devices })
+    }
+
+    fn device(&self, index: usize) -> Result<&BacklightDevice, String> {
+        self.devices.get(index).ok_or_else(|| format!("背光设备序号 {} 不存在", index))
+    }
+}
+
+impl DisplayBackend for BacklightBackend {
+    fn name(&self) -> &'static str {
+        "backlight"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            power: true,
+            query_state: true,
+            brightness: true,
+            per_display: true,
+        }
+    }
+
+    fn set_power(&self, on: bool) -> Result<(), String> {
+        // 尽量操作所有设备，返回遇到的第一个错误
+        let mut result = Ok(());
+        for device in &self.devices {
+            if let Err(e) = device.set_power(on) {
+                crate::log_warn(&format!("背光设备 {} 操作失败: {}", device.name(), e));
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
+    fn query_state(&self) -> ScreenState {
+        let mut any_known = false;
+        for device in &self.devices {
+            match device.is_on() {
+                Ok(true) => return ScreenState::On,
+                Ok(false) => any_known = true,
+                Err(e) => crate::log_warn(&format!("读取背光设备 {} 状态失败: {}", device.name(), e)),
+            }
+        }
+        if any_known {
+            ScreenState::Off
+        } else {
+            ScreenState::Unknown
+        }
+    }
+
+    fn displays(&self) -> Result<Vec<DisplayInfo>, String> {
+        Ok(self
+            .devices
+            .iter()
+            .enumerate()
+            .map(|(index, device)| DisplayInfo {
+                index,
+                name: device.name().to_string(),
+                serial: None,
+                connector: device.connector(),
+                resolution: device.resolution(),
+            })
+            .collect())
+    }
+
+    fn set_power_for(&self, displays: &[usize], on: bool) -> Result<(), String> {
+        for &index in displays {
+            self.device(index)?.set_power(on)?;
+        }
+        Ok(())
+    }
+
+    fn query_state_for(&self, display: usize) -> ScreenState {
+        match self.devices.get(display).map(|d| d.is_on()) {
+            Some(Ok(true)) => ScreenState::On,
+            Some(Ok(false)) => ScreenState::Off,
+            _ => ScreenState::Unknown,
+        }
+    }
+
+    /// 读取全部背光设备亮度百分比的平均值
+    ///
+    /// `set_brightness` 会把所有设备设为同一百分比，平均值在此之后与设置值一致；
+    /// 设备被单独调节过时，平均值比只读取第一个设备更能反映整体亮度。
+    /// 无法读取的设备不参与计算，全部无法读取时返回第一个错误。
+    fn brightness(&self) -> Result<u8, String> {
+        super::mean_brightness(self.devices.iter().map(|device| {
+            device.brightness_percent().map_err(|e| {
+                crate::log_warn(&format!("读取背光设备 {} 亮度失败: {}", device.name(), e));
+                e
+            })
+        }))
+    }
+
+    fn set_brightness(&self, percent: u8) -> Result<(), String> {
+        for device in &self.devices {
+            device.set_brightness_percent(percent)?;
+        }
+        Ok(())
+    }
+
+    fn brightness_for(&self, display: usize) -> Result<u8, String> {
+        self.device(display)?.brightness_percent()
+    }
+
+    fn set_brightness_for(&self, displays: &[usize], percent: u8) -> Result<(), String> {
+        for &index in displays {
+            self.device(index)?.set_brightness_percent(percent)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 在临时目录中创建 `<root>/<name>` 背光设备，`bl_power` 为 `None` 时不创建该文件
+    fn fake_device(root: &Path, name: &str, bl_power: Option<u32>, brightness: u32, max: u32) -> PathBuf {
+        let dir = root.join(name);
+        fs::create_dir_all(&dir).unwrap();
+        if let Some(power) = bl_power {
+            fs::write(dir.join("bl_power"), format!("{}\n", power)).unwrap();
+        }
+        fs::write(dir.join("brightness"), format!("{}\n", brightness)).unwrap();
+        fs::write(dir.join("max_brightness"), format!("{}\n", max)).unwrap();
+        dir
+    }
+
+    fn read(dir: &Path, file: &str) -> u32 {
+        fs::read_to_string(dir.join(file)).unwrap().trim().parse().unwrap()
+    }
+
+    #[test]
+    fn power_via_bl_power() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = fake_device(root.path(), "intel_backlight", Some(BL_POWER_ON), 300, 1000);
+        fs::write(dir.join("actual_brightness"), "300\n").unwrap();
+        let config = BacklightConfig {
+            sysfs_root: root.path().to_path_buf(),
+            ..Default::default()
+        };
+        let backend = BacklightBackend::new(&config).unwrap();
+        assert_eq!(backend.query_state(), ScreenState::On);
+
+        backend.set_power(false).unwrap();
+        assert_eq!(read(&dir, "bl_power"), BL_POWER_OFF);
+        assert_eq!(read(&dir, "brightness"), 300);
+        assert_eq!(backend.query_state(), ScreenState::Off);
+
+        backend.set_power(true).unwrap();
+        assert_eq!(read(&dir, "bl_power"), BL_POWER_ON);
+        assert_eq!(backend.query_state(), ScreenState::On);
+    }
+
+    #[test]
+    fn power_via_brightness() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = fake_device(root.path(), "panel", None, 120, 255);
+        let panel = BacklightDevice::new("panel".to_string(), dir.clone());
+        assert!(panel.is_on().unwrap());
+
+        panel.set_power(false).unwrap();
+        assert_eq!(read(&dir, "brightness"), 0);
+        assert!(!panel.is_on().unwrap());
+        // 重复关闭不能覆盖已保存的亮度
+        panel.set_power(false).unwrap();
+        panel.set_power(true).unwrap();
+        assert_eq!(read(&dir, "brightness"), 120);
+
+        // 启动时已经熄灭、没有保存过亮度的设备开启到最大亮度
+        let dir = fake_device(root.path(), "dark", None, 0, 255);
+        let dark = BacklightDevice::new("dark".to_string(), dir.clone());
+        assert!(!dark.is_on().unwrap());
+        dark.set_power(true).unwrap();
+        assert_eq!(read(&dir, "brightness"), 255);
+    }
+
+    #[test]
+    fn is_on_prefers_actual_brightness() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = fake_device(root.path(), "panel", Some(BL_POWER_ON), 100, 255);
+        fs::write(dir.join("actual_brightness"), "0\n").unwrap();
+        let device = BacklightDevice::new("panel".to_string(), dir);
+        assert!(!device.is_on().unwrap());
+    }
+
+    #[test]
+    fn brightness_is_mean_of_readable_devices() {
+        let root = tempfile::tempdir().unwrap();
+        let a = fake_device(root.path(), "a", Some(BL_POWER_ON), 20, 100);
+        let b = fake_device(root.path(), "b", Some(BL_POWER_ON), 255, 255);
+        let config = BacklightConfig {
+            sysfs_root: root.path().to_path_buf(),
+            ..Default::default()
+        };
+        let backend = BacklightBackend::new(&config).unwrap();
+        assert_eq!(backend.brightness().unwrap(), 60);
+
+        backend.set_brightness(40).unwrap();
+        assert_eq!(backend.brightness().unwrap(), 40);
+
+        fs::write(b.join("brightness"), "255\n").unwrap();
+        fs::write(a.join("max_brightness"), "0\n").unwrap();
+        assert_eq!(backend.brightness().unwrap(), 100);
+        fs::write(b.join("max_brightness"), "0\n").unwrap();
+        let err = backend.brightness().unwrap_err();
+        assert!(err.contains("max_brightness 为 0"), "{}", err);
+    }
+
+    #[test]
+    fn brightness_for_selected_devices() {
+        let root = tempfile::tempdir().unwrap();
+        let a = fake_device(root.path(), "a", Some(BL_POWER_ON), 20, 100);
+        let b = fake_device(root.path(), "b", Some(BL_POWER_ON), 255, 255);
+        let config = BacklightConfig {
+            sysfs_root: root.path().to_path_buf(),
+            ..Default::default()
+        };
+        let backend = BacklightBackend::new(&config).unwrap();
+        assert_eq!(backend.brightness_for(1).unwrap(), 100);
+
+        backend.set_brightness_for(&[1], 50).unwrap();
+        assert_eq!(read(&a, "brightness"), 20);
+        assert_eq!(read(&b, "brightness"), 128);
+        assert!(backend.set_brightness_for(&[2], 50).is_err());
+    }
+
+    #[test]
+    fn discover_filters_by_name() {
+        let root = tempfile::tempdir().unwrap();
+        fake_device(root.path(), "b", Some(BL_POWER_ON), 1, 10);
+        fake_device(root.path(), "a", Some(BL_POWER_ON), 1, 10);
+        fs::create_dir_all(root.path().join("incomplete")).unwrap();
+        let names = |devices: Vec<BacklightDevice>| devices.iter().map(|d| d.name().to_string()).collect::<Vec<_>>();
+        assert_eq!(names(discover(root.path(), &[]).unwrap()), ["a", "b"]);
+        assert_eq!(names(discover(root.path(), &["b".to_string()]).unwrap()), ["b"]);
+    }
+
+    #[test]
+    fn no_device_is_an_error() {
+        let root = tempfile::tempdir().unwrap();
+        fs::create_dir_all(root.path().join("incomplete")).unwrap();
+        let config = BacklightConfig {
+            sysfs_root: root.path().to_path_buf(),
+            ..Default::default()
+        };
+        let err = BacklightBackend::new(&config).err().unwrap();
+        assert!(err.contains("未找到可用的背光设备"), "{}", err);
+
+        let missing = BacklightConfig {
+            sysfs_root: root.path().join("missing"),
+            ..Default::default()
+        };
+        assert!(BacklightBackend::new(&missing).is_err());
+    }
+}
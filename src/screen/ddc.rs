@@ -0,0 +1,729 @@
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::os::unix::io::AsRawFd;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+use super::{drm_connector_name, edid_descriptor_text, edid_serial, Capabilities, DdcConfig, DisplayBackend, DisplayInfo, Resolution, ScreenState};
+
+/// DDC/CI 从机地址
+const DDC_ADDR: u16 = 0x37;
+/// EDID EEPROM 地址
+const EDID_ADDR: u16 = 0x50;
+/// 主机发送地址（写入数据的第一个字节）
+const HOST_ADDR: u8 = 0x51;
+/// 计算主机请求校验和时使用的目标地址（0x37 << 1）
+const DEST_ADDR_WRITE: u8 = 0x6E;
+/// 计算显示器应答校验和时使用的虚拟主机地址
+const HOST_ADDR_REPLY: u8 = 0x50;
+
+const OP_GET_VCP: u8 = 0x01;
+const OP_GET_VCP_REPLY: u8 = 0x02;
+const OP_SET_VCP: u8 = 0x03;
+
+/// VCP 0x10：亮度
+pub const VCP_BRIGHTNESS: u8 = 0x10;
+/// VCP 0x60：输入源
+pub const VCP_INPUT_SOURCE: u8 = 0x60;
+/// VCP 0xD6：电源模式（1 开启，2 待机，3 挂起，4 关闭，5 断电）
+pub const VCP_POWER_MODE: u8 = 0xD6;
+/// 电源模式：开启
+const POWER_MODE_ON: u16 = 1;
+
+/// 显示器处理请求所需的时间（DDC/CI 规范建议值）
+const REPLY_DELAY: Duration = Duration::from_millis(40);
+const SET_DELAY: Duration = Duration::from_millis(50);
+/// 显示器忙碌或应答损坏时的重试次数
+const MAX_ATTEMPTS: u32 = 3;
+
+/// `ioctl` 请求：设置后续读写使用的从机地址（linux/i2c-dev.h）
+const I2C_SLAVE: u64 = 0x0703;
+
+/// I2C 传输抽象
+///
+/// 真实设备使用 `/dev/i2c-*`，测试时可以替换为在内存中模拟 DDC 应答的实现。
+pub trait I2cTransport: Send {
+    /// 向指定从机地址写入数据
+    fn write(&mut self, addr: u16, data: &[u8]) -> io::Result<()>;
+
+    /// 从指定从机地址读取数据填满 `buf`
+    fn read(&mut self, addr: u16, buf: &mut [u8]) -> io::Result<()>;
+}
+
+/// Linux `i2c-dev` 设备
+pub struct I2cDev {
+    file: File,
+}
+
+impl I2cDev {
+    /// 打开 I2C 总线设备（如 `/dev/i2c-4`）
+    pub fn open(path: &Path) -> io::Result<Self> {
+        let file = OpenOptions::new().read(true).write(true).open(path)?;
+        Ok(Self { file })
+    }
+
+    fn select(&self, addr: u16) -> io::Result<()> {
+        // SAFETY: fd 在 self.file 的生命周期内有效，I2C_SLAVE 只读取整数参数
+        let ret = unsafe { libc::ioctl(self.file.as_raw_fd(), I2C_SLAVE as _, addr as libc::c_ulong) };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(())
+    }
+}
+
+impl I2cTransport for I2cDev {
+    fn write(&mut self, addr: u16, data: &[u8]) -> io::Result<()> {
+        self.select(addr)?;
+        self.file.write_all(data)
+    }
+
+    fn read(&mut self, addr: u16, buf: &mut [u8]) -> io::Result<()> {
+        self.select(addr)?;
+        self.file.read_exact(buf)
+    }
+}
+
+/// VCP 功能的当前值与最大值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VcpValue {
+    pub current: u16,
+    pub max: u16,
+}
+
+/// 异或校验和
+fn checksum(init: u8, bytes: &[u8]) -> u8 {
+    bytes.iter().fold(init, |acc, b| acc ^ b)
+}
+
+/// 构造 Set VCP Feature 请求
+pub fn encode_set_vcp(code: u8, value: u16) -> Vec<u8> {
+    let [hi, lo] = value.to_be_bytes();
+    let mut packet = vec![HOST_ADDR, 0x84, OP_SET_VCP, code, hi, lo];
+    packet.push(checksum(DEST_ADDR_WRITE, &packet));
+    packet
+}
+
+/// 构造 Get VCP Feature 请求
+pub fn encode_get_vcp(code: u8) -> Vec<u8> {
+    let mut packet = vec![HOST_ADDR, 0x82, OP_GET_VCP, code];
+    packet.push(checksum(DEST_ADDR_WRITE, &packet));
+    packet
+}
+
+/// 解析 Get VCP Feature 应答（11 字节）
+///
+/// # Arguments
+/// * `code` - 请求的 VCP 代码，用于校验应答是否对应
+/// * `reply` - 从显示器读取的原始数据
+pub fn decode_get_vcp_reply(code: u8, reply: &[u8]) -> Result<VcpValue, String> {
+    if reply.len() < 3 {
+        return Err("DDC 应答长度不足".to_string());
+    }
+    let len = (reply[1] & 0x7F) as usize;
+    if reply[1] & 0x80 == 0 {
+        return Err(format!("DDC 应答长度字节无效: {:#04x}", reply[1]));
+    }
+    if len == 0 {
+        return Err("显示器返回空应答（忙碌）".to_string());
+    }
+    if len != 8 || reply.len() < 11 {
+        return Err(format!("DDC 应答长度异常: {}", len));
+    }
+    if checksum(HOST_ADDR_REPLY, &reply[..10]) != reply[10] {
+        return Err("DDC 应答校验和错误".to_string());
+    }
+    if reply[2] != OP_GET_VCP_REPLY || reply[4] != code {
+        return Err(format!("DDC 应答不对应 VCP {:#04x}", code));
+    }
+    if reply[3] != 0 {
+        return Err(format!("显示器不支持 VCP {:#04x}", code));
+    }
+    Ok(VcpValue {
+        max: u16::from_be_bytes([reply[6], reply[7]]),
+        current: u16::from_be_bytes([reply[8], reply[9]]),
+    })
+}
+
+/// 单条 I2C 总线上的 DDC/CI 通道
+pub struct DdcChannel {
+    transport: Box<dyn I2cTransport>,
+}
+
+impl DdcChannel {
+    pub fn new(transport: Box<dyn I2cTransport>) -> Self {
+        Self { transport }
+    }
+
+    /// 读取显示器 EDID 的基础块（128 字节）
+    pub fn read_edid(&mut self) -> Result<Vec<u8>, String> {
+        let mut edid = vec![0u8; 128];
+        self.transport
+            .write(EDID_ADDR, &[0])
+            .and_then(|_| self.transport.read(EDID_ADDR, &mut edid))
+            .map_err(|e| format!("读取 EDID 失败: {}", e))?;
+        if edid[..8] != [0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00] {
+            return Err("EDID 头部无效".to_string());
+        }
+        Ok(edid)
+    }
+
+    /// 设置 VCP 功能值
+    pub fn set_vcp(&mut self, code: u8, value: u16) -> Result<(), String> {
+        self.transport
+            .write(DDC_ADDR, &encode_set_vcp(code, value))
+            .map_err(|e| format!("写入 VCP {:#04x} 失败: {}", code, e))?;
+        thread::sleep(SET_DELAY);
+        Ok(())
+    }
+
+    /// 读取 VCP 功能值，显示器忙碌或应答损坏时重试
+    pub fn get_vcp(&mut self, code: u8) -> Result<VcpValue, String> {
+        let mut last_error = String::new();
+        for _ in 0..MAX_ATTEMPTS {
+            if let Err(e) = self.transport.write(DDC_ADDR, &encode_get_vcp(code)) {
+                last_error = format!("请求 VCP {:#04x} 失败: {}", code, e);
+                continue;
+            }
+            thread::sleep(REPLY_DELAY);
+
+            let mut reply = [0u8; 11];
+            if let Err(e) = self.transport.read(DDC_ADDR, &mut reply) {
+                last_error = format!("读取 VCP {:#04x} 应答失败: {}", code, e);
+                continue;
+            }
+            match decode_get_vcp_reply(code, &reply) {
+                Ok(value) => return Ok(value),
+                Err(e) => last_error = e,
+            }
+        }
+        Err(last_error)
+    }
+}
+
+/// 从 EDID 第一个详细时序描述符（推荐时序）读取分辨率
+fn edid_preferred_resolution(edid: &[u8]) -> Option<Resolution> {
+    let d = &edid[54..72];
+    // 像素时钟为 0 表示这是文本等描述符而不是时序
+    if d[0] == 0 && d[1] == 0 {
+        return None;
+    }
+    Some(Resolution {
+        width: d[2] as u32 | ((d[4] as u32 & 0xF0) << 4),
+        height: d[5] as u32 | ((d[7] as u32 & 0xF0) << 4),
+    })
+}
+
+/// 查找 I2C 总线所属的 DRM 显示接口（如 "/dev/i2c-4" 对应 "DP-1"）
+fn drm_connector_for_bus(bus: &Path) -> Option<String> {
+    let bus_name = bus.file_name()?.to_string_lossy().into_owned();
+    let entries = fs::read_dir("/sys/class/drm").ok()?;
+    entries.flatten().find_map(|entry| {
+        let path = entry.path();
+        // HDMI/DVI 接口通过 ddc 链接指向总线，DP 接口的 AUX 总线是接口目录下的子目录
+        let via_link = fs::read_link(path.join("ddc"))
+            .ok()
+            .and_then(|target| target.file_name().map(|n| n.to_string_lossy() == bus_name))
+            .unwrap_or(false);
+        if via_link || path.join(&bus_name).is_dir() {
+            Some(drm_connector_name(&entry.file_name().to_string_lossy()))
+        } else {
+            None
+        }
+    })
+}
+
+/// 通过 DDC/CI 控制的单台显示器
+pub struct DdcMonitor {
+    name: String,
+    serial: Option<String>,
+    connector: Option<String>,
+    resolution: Option<Resolution>,
+    channel: Mutex<DdcChannel>,
+}
+
+impl DdcMonitor {
+    /// 在给定通道上探测显示器：读取 EDID 并确认支持 VCP 0xD6
+    ///
+    /// # Arguments
+    /// * `bus` - 显示器所在的 I2C 总线，EDID 中没有型号名时作为名称
+    /// * `connector` - 总线所属的显示接口（如 "DP-1"）
+    /// * `channel` - 该总线上的 DDC/CI 通道
+    pub fn probe(bus: &Path, connector: Option<String>, mut channel: DdcChannel) -> Result<Self, String> {
+        let edid = channel.read_edid()?;
+        channel.get_vcp(VCP_POWER_MODE)?;
+        let name = edid_descriptor_text(&edid, 0xFC).unwrap_or_else(|| bus.to_string_lossy().into_owned());
+        Ok(Self {
+            name,
+            serial: edid_serial(&edid),
+            connector,
+            resolution: edid_preferred_resolution(&edid),
+            channel: Mutex::new(channel),
+        })
+    }
+
+    /// 显示器名称（EDID 中的型号名）
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn get_vcp(&self, code: u8) -> Result<VcpValue, String> {
+        self.channel.lock().unwrap().get_vcp(code)
+    }
+
+    pub fn set_vcp(&self, code: u8, value: u16) -> Result<(), String> {
+        self.channel.lock().unwrap().set_vcp(code, value)
+    }
+
+    /// 当前亮度占最大亮度的百分比（VCP 0x10）
+    pub fn brightness_percent(&self) -> Result<u8, String> {
+        let value = self.get_vcp(VCP_BRIGHTNESS)?;
+        if value.max == 0 {
+            return Err(format!("显示器 {} 的最大亮度为 0", self.name));
+        }
+        let percent = (value.current as u32 * 100 + value.max as u32 / 2) / value.max as u32;
+        Ok(percent.min(100) as u8)
+    }
+
+    /// 按最大亮度的百分比设置亮度（VCP 0x10）
+    pub fn set_brightness_percent(&self, percent: u8) -> Result<(), String> {
+        let max = self.get_vcp(VCP_BRIGHTNESS)?.max as u32;
+        self.set_vcp(VCP_BRIGHTNESS, ((max * percent.min(100) as u32 + 50) / 100) as u16)
+    }
+}
+
+/// 列出 `/dev` 下的全部 I2C 总线
+fn scan_buses() -> Result<Vec<PathBuf>, String> {
+    let entries = fs::read_dir("/dev").map_err(|e| format!("无法读取 /dev: {}", e))?;
+    let mut buses: Vec<PathBuf> = entries
+        .flatten()
+        .filter(|e| e.file_name().to_string_lossy().starts_with("i2c-"))
+        .map(|e| e.path())
+        .collect();
+    buses.sort();
+    Ok(buses)
+}
+
+/// DDC/CI 后端
+///
+/// 通过 VCP 0xD6 真正让显示器进入待机/关闭，而不是仅停止输出信号，
+/// 避免外接显示器停留在亮着的“无信号”画面。
+pub struct DdcBackend {
+    monitors: Vec<DdcMonitor>,
+    off_mode: u16,
+    on_input: Option<u16>,
+}
+
+impl DdcBackend {
+    /// 打开配置中的 I2C 总线（未配置时扫描全部总线）并探测支持 DDC/CI 的显示器
+    ///
+    /// # Arguments
+    /// * `config` - `[display.ddc]` 配置段
+    pub fn new(config: &DdcConfig) -> Result<Self, String> {
+        let buses = if config.buses.is_empty() {
+            scan_buses()?
+        } else {
+            config.buses.clone()
+        };
+
+        let mut monitors = Vec::new();
+        for bus in buses {
+            let channel = match I2cDev::open(&bus) {
+                Ok(dev) => DdcChannel::new(Box::new(dev)),
+                Err(e) => {
+                    crate::log_warn(&format!("无法打开 I2C 总线 {:?}: {}", bus, e));
+                    continue;
+                }
+            };
+            match DdcMonitor::probe(&bus, drm_connector_for_bus(&bus), channel) {
+                Ok(monitor) => {
+                    crate::log_info(&format!("发现 DDC/CI 显示器: {} ({:?})", monitor.name(), bus));
+                    monitors.push(monitor);
+                }
+                // 扫描时大部分总线上没有显示器，不作为警告输出
+                Err(_) if config.buses.is_empty() => {}
+                Err(e) => crate::log_warn(&format!("I2C 总线 {:?} 上未找到 DDC/CI 显示器: {}", bus, e)),
+            }
+        }
+
+        Self::with_monitors(monitors, config)
+    }
+
+    /// 使用已探测好的显示器创建后端
+    pub fn with_monitors(monitors: Vec<DdcMonitor>, config: &DdcConfig) -> Result<Self, String> {
+        if monitors.is_empty() {
+            return Err("未找到支持 DDC/CI 的显示器".to_string());
+        }
+        Ok(Self {
+            monitors,
+            off_mode: config.off_mode.vcp_value(),
+            on_input: config.on_input,
+        })
+    }
+
+    fn monitor(&self, index: usize) -> Result<&DdcMonitor, String> {
+        self.monitors.get(index).ok_or_else(|| format!("显示器序号 {} 不存在", index))
+    }
+
+    fn power_on(&self, monitor: &DdcMonitor) -> Result<(), String> {
+        monitor.set_vcp(VCP_POWER_MODE, POWER_MODE_ON)?;
+        if let Some(input) = self.on_input {
+            monitor.set_vcp(VCP_INPUT_SOURCE, input)?;
+        }
+        Ok(())
+    }
+
+    fn set_monitor_power(&self, monitor: &DdcMonitor, on: bool) -> Result<(), String> {
+        if on {
+            self.power_on(monitor)
+        } else {
+            monitor.set_vcp(VCP_POWER_MODE, self.off_mode)
+        }
+    }
+
+    fn monitor_state(monitor: &DdcMonitor) -> Result<ScreenState, String> {
+        let value = monitor.get_vcp(VCP_POWER_MODE)?;
+        Ok(if value.current == POWER_MODE_ON {
+            ScreenState::On
+        } else {
+            ScreenState::Off
+        })
+    }
+}
+
+impl DisplayBackend for DdcBackend {
+    fn name(&self) -> &'static str {
+        "ddc"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            power: true,
+            query_state: true,
+            brightness: true,
+            per_display: true,
+        }
+    }
+
+    fn set_power(&self, on: bool) -> Result<(), String> {
+        // 尽量操作所有显示器，返回遇到的第一个错误
+        let mut result = Ok(());
+        for monitor in &self.monitors {
+            if let Err(e) = self.set_monitor_power(monitor, on) {
+                crate::log_warn(&format!("显示器 {} DDC/CI 操作失败: {}", monitor.name(), e));
+                if result.is_ok() {
+                    result = Err(e);
+                }
+            }
+        }
+        result
+    }
+
+    fn query_state(&self) -> ScreenState {
+        let mut any_known = false;
+        for monitor in &self.monitors {
+            match Self::monitor_state(monitor) {
+                Ok(ScreenState::On) => return ScreenState::On,
+                Ok(_) => any_known = true,
+                Err(e) => crate::log_warn(&format!("读取显示器 {} 电源模式失败: {}", monitor.name(), e)),
+            }
+        }
+        if any_known {
+            ScreenState::Off
+        } else {
+            ScreenState::Unknown
+        }
+    }
+
+    fn displays(&self) -> Result<Vec<DisplayInfo>, String> {
+        Ok(self
+            .monitors
+            .iter()
+            .enumerate()
+            .map(|(index, monitor)| DisplayInfo {
+                index,
+                name: monitor.name.clone(),
+                serial: monitor.serial.clone(),
+                connector: monitor.connector.clone(),
+                resolution: monitor.resolution,
+            })
+            .collect())
+    }
+
+    fn set_power_for(&self, displays: &[usize], on: bool) -> Result<(), String> {
+        for &index in displays {
+            self.set_monitor_power(self.monitor(index)?, on)?;
+        }
+        Ok(())
+    }
+
+    fn query_state_for(&self, display: usize) -> ScreenState {
+        match self.monitors.get(display).map(Self::monitor_state) {
+            Some(Ok(state)) => state,
+            _ => ScreenState::Unknown,
+        }
+    }
+
+    fn brightness(&self) -> Result<u8, String> {
+        super::mean_brightness(self.monitors.iter().map(|monitor| {
+            monitor.brightness_percent().map_err(|e| {
+                crate::log_warn(&format!("读取显示器 {} 亮度失败: {}", monitor.name(), e));
+                e
+            })
+        }))
+    }
+
+    fn set_brightness(&self, percent: u8) -> Result<(), String> {
+        for monitor in &self.monitors {
+            monitor.set_brightness_percent(percent)?;
+        }
+        Ok(())
+    }
+
+    fn brightness_for(&self, display: usize) -> Result<u8, String> {
+        self.monitor(display)?.brightness_percent()
+    }
+
+    fn set_brightness_for(&self, displays: &[usize], percent: u8) -> Result<(), String> {
+        for &index in displays {
+            self.monitor(index)?.set_brightness_percent(percent)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, VecDeque};
+    use std::sync::Arc;
+
+    /// 模拟显示器对下一次 Get VCP 请求的异常应答
+    #[derive(Debug, Clone, Copy)]
+    enum Fault {
+        /// 返回空应答（显示器忙碌）
+        Busy,
+        /// 读取时只返回部分数据
+        Short,
+    }
+
+    /// 模拟的显示器状态，在测试与传输之间共享
+    #[derive(Default)]
+    struct FakeMonitor {
+        /// VCP 代码对应的（当前值，最大值），不在表中的代码视为不支持
+        vcp: HashMap<u8, (u16, u16)>,
+        /// 下一次读取返回的应答
+        reply: Option<Vec<u8>>,
+        faults: VecDeque<Fault>,
+        short_read: bool,
+        get_requests: usize,
+    }
+
+    /// 在内存中解析 Set/Get VCP 请求并生成真实格式应答的传输
+    struct FakeTransport(Arc<Mutex<FakeMonitor>>);
+
+    fn edid() -> Vec<u8> {
+        let mut edid = vec![0u8; 128];
+        edid[..8].copy_from_slice(&[0x00, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0x00]);
+        edid[12..16].copy_from_slice(&1234u32.to_le_bytes());
+        // 推荐时序 2560x1440
+        edid[54..72].copy_from_slice(&[
+            0x56, 0x5E, 0x00, 0xA0, 0xA0, 0xA0, 0x29, 0x50, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+        ]);
+        edid[72..77].copy_from_slice(&[0, 0, 0, 0xFC, 0]);
+        edid[77..90].copy_from_slice(b"DELL U2720Q\n ");
+        edid
+    }
+
+    fn reply_packet(code: u8, result: u8, max: u16, current: u16) -> Vec<u8> {
+        let [max_hi, max_lo] = max.to_be_bytes();
+        let [cur_hi, cur_lo] = current.to_be_bytes();
+        let mut packet = vec![
+            DEST_ADDR_WRITE,
+            0x88,
+            OP_GET_VCP_REPLY,
+            result,
+            code,
+            0x00,
+            max_hi,
+            max_lo,
+            cur_hi,
+            cur_lo,
+        ];
+        packet.push(checksum(HOST_ADDR_REPLY, &packet));
+        packet
+    }
+
+    impl I2cTransport for FakeTransport {
+        fn write(&mut self, addr: u16, data: &[u8]) -> io::Result<()> {
+            if addr == EDID_ADDR {
+                return Ok(());
+            }
+            assert_eq!(addr, DDC_ADDR);
+            assert_eq!(checksum(DEST_ADDR_WRITE, data), 0, "请求校验和错误: {:02x?}", data);
+            let mut monitor = self.0.lock().unwrap();
+            match data[2] {
+                OP_GET_VCP => {
+                    let code = data[3];
+                    monitor.get_requests += 1;
+                    monitor.short_read = false;
+                    monitor.reply = Some(match monitor.faults.pop_front() {
+                        Some(Fault::Busy) => {
+                            let mut packet = vec![DEST_ADDR_WRITE, 0x80];
+                            packet.push(checksum(HOST_ADDR_REPLY, &packet));
+                            packet.resize(11, 0);
+                            packet
+                        }
+                        Some(Fault::Short) => {
+                            monitor.short_read = true;
+                            Vec::new()
+                        }
+                        None => match monitor.vcp.get(&code) {
+                            Some(&(current, max)) => reply_packet(code, 0, max, current),
+                            None => reply_packet(code, 1, 0, 0),
+                        },
+                    });
+                }
+                OP_SET_VCP => {
+                    let value = u16::from_be_bytes([data[4], data[5]]);
+                    if let Some(entry) = monitor.vcp.get_mut(&data[3]) {
+                        entry.0 = value;
+                    }
+                }
+                op => panic!("未知的 DDC 操作码: {:#04x}", op),
+            }
+            Ok(())
+        }
+
+        fn read(&mut self, addr: u16, buf: &mut [u8]) -> io::Result<()> {
+            if addr == EDID_ADDR {
+                buf.copy_from_slice(&edid()[..buf.len()]);
+                return Ok(());
+            }
+            let mut monitor = self.0.lock().unwrap();
+            if monitor.short_read {
+                return Err(io::Error::new(io::ErrorKind::UnexpectedEof, "short read"));
+            }
+            let reply = monitor.reply.take().expect("读取前没有发送请求");
+            buf.copy_from_slice(&reply);
+            Ok(())
+        }
+    }
+
+    fn fake_monitor() -> Arc<Mutex<FakeMonitor>> {
+        Arc::new(Mutex::new(FakeMonitor {
+            vcp: HashMap::from([(VCP_POWER_MODE, (POWER_MODE_ON, 5)), (VCP_BRIGHTNESS, (50, 100))]),
+            ..Default::default()
+        }))
+    }
+
+    fn channel(monitor: &Arc<Mutex<FakeMonitor>>) -> DdcChannel {
+        DdcChannel::new(Box::new(FakeTransport(monitor.clone())))
+    }
+
+    #[test]
+    fn encode_set_vcp_checksum() {
+        assert_eq!(encode_set_vcp(VCP_POWER_MODE, 4), [0x51, 0x84, 0x03, 0xD6, 0x00, 0x04, 0x6A]);
+        assert_eq!(checksum(DEST_ADDR_WRITE, &encode_set_vcp(VCP_BRIGHTNESS, 0x1234)), 0);
+    }
+
+    #[test]
+    fn encode_get_vcp_checksum() {
+        assert_eq!(encode_get_vcp(VCP_BRIGHTNESS), [0x51, 0x82, 0x01, 0x10, 0xAC]);
+        assert_eq!(checksum(DEST_ADDR_WRITE, &encode_get_vcp(VCP_POWER_MODE)), 0);
+    }
+
+    #[test]
+    fn decode_valid_reply() {
+        let value = decode_get_vcp_reply(VCP_BRIGHTNESS, &reply_packet(VCP_BRIGHTNESS, 0, 100, 42)).unwrap();
+        assert_eq!(value, VcpValue { current: 42, max: 100 });
+    }
+
+    #[test]
+    fn decode_rejects_bad_checksum() {
+        let mut reply = reply_packet(VCP_BRIGHTNESS, 0, 100, 42);
+        reply[10] ^= 0xFF;
+        let err = decode_get_vcp_reply(VCP_BRIGHTNESS, &reply).unwrap_err();
+        assert!(err.contains("校验和"), "{}", err);
+    }
+
+    #[test]
+    fn decode_rejects_wrong_opcode() {
+        let mut reply = reply_packet(VCP_BRIGHTNESS, 0, 100, 42);
+        reply[2] = OP_SET_VCP;
+        reply[10] = checksum(HOST_ADDR_REPLY, &reply[..10]);
+        let err = decode_get_vcp_reply(VCP_BRIGHTNESS, &reply).unwrap_err();
+        assert!(err.contains("不对应"), "{}", err);
+
+        let reply = reply_packet(VCP_POWER_MODE, 0, 5, 1);
+        assert!(decode_get_vcp_reply(VCP_BRIGHTNESS, &reply).is_err());
+    }
+
+    #[test]
+    fn decode_rejects_unsupported_vcp() {
+        let err = decode_get_vcp_reply(VCP_INPUT_SOURCE, &reply_packet(VCP_INPUT_SOURCE, 1, 0, 0)).unwrap_err();
+        assert!(err.contains("不支持"), "{}", err);
+    }
+
+    #[test]
+    fn get_vcp_retries_busy_and_short_replies() {
+        let monitor = fake_monitor();
+        monitor.lock().unwrap().faults.extend([Fault::Busy, Fault::Short]);
+        let value = channel(&monitor).get_vcp(VCP_BRIGHTNESS).unwrap();
+        assert_eq!(value, VcpValue { current: 50, max: 100 });
+        assert_eq!(monitor.lock().unwrap().get_requests, 3);
+    }
+
+    #[test]
+    fn get_vcp_gives_up_after_max_attempts() {
+        let monitor = fake_monitor();
+        monitor.lock().unwrap().faults.extend([Fault::Busy; MAX_ATTEMPTS as usize]);
+        let err = channel(&monitor).get_vcp(VCP_BRIGHTNESS).unwrap_err();
+        assert!(err.contains("忙碌"), "{}", err);
+        assert_eq!(monitor.lock().unwrap().get_requests, MAX_ATTEMPTS as usize);
+    }
+
+    #[test]
+    fn probe_reads_edid() {
+        let monitor = fake_monitor();
+        let probed = DdcMonitor::probe(Path::new("/dev/i2c-4"), Some("DP-1".to_string()), channel(&monitor)).unwrap();
+        assert_eq!(probed.name(), "DELL U2720Q");
+        assert_eq!(probed.serial.as_deref(), Some("1234"));
+        assert_eq!(probed.resolution, Some(Resolution { width: 2560, height: 1440 }));
+    }
+
+    #[test]
+    fn power_mode_round_trip() {
+        let monitor = fake_monitor();
+        let config = DdcConfig::default();
+        let probed = DdcMonitor::probe(Path::new("/dev/i2c-4"), None, channel(&monitor)).unwrap();
+        let backend = DdcBackend::with_monitors(vec![probed], &config).unwrap();
+        assert_eq!(backend.query_state(), ScreenState::On);
+
+        backend.set_power(false).unwrap();
+        assert_eq!(monitor.lock().unwrap().vcp[&VCP_POWER_MODE].0, config.off_mode.vcp_value());
+        assert_eq!(backend.query_state(), ScreenState::Off);
+
+        backend.set_power(true).unwrap();
+        assert_eq!(monitor.lock().unwrap().vcp[&VCP_POWER_MODE].0, POWER_MODE_ON);
+        assert_eq!(backend.query_state(), ScreenState::On);
+    }
+
+    #[test]
+    fn brightness_is_mean_of_monitors() {
+        let first = fake_monitor();
+        let second = fake_monitor();
+        second.lock().unwrap().vcp.insert(VCP_BRIGHTNESS, (10, 50));
+        let monitors = [&first, &second]
+            .map(|monitor| DdcMonitor::probe(Path::new("/dev/i2c-4"), None, channel(monitor)).unwrap());
+        let backend = DdcBackend::with_monitors(monitors.into(), &DdcConfig::default()).unwrap();
+        assert_eq!(backend.brightness().unwrap(), 35);
+        assert_eq!(backend.brightness_for(1).unwrap(), 20);
+
+        // 读取失败的显示器不参与平均
+        first.lock().unwrap().faults.extend([Fault::Busy; MAX_ATTEMPTS as usize]);
+        assert_eq!(backend.brightness().unwrap(), 20);
+    }
+}
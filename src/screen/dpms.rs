@@ -0,0 +1,205 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::dpms::{self, ConnectionExt as _, DPMSMode};
+use x11rb::rust_connection::RustConnection;
+
+use super::{Capabilities, DisplayBackend, DisplayInfo, DpmsConfig, DpmsLevel, Resolution, ScreenState};
+
+/// X11 DPMS 后端
+///
+/// 通过 X Server 的 DPMS 扩展强制设置显示器电源等级，
+/// 并读取 X Server 报告的实际电源等级。
+pub struct DpmsBackend {
+    conn: RustConnection,
+    screen: usize,
+    off_level: DpmsLevel,
+    /// DPMS 原本被禁用、为关闭显示器而临时启用
+    enabled_by_us: AtomicBool,
+}
+
+impl DpmsBackend {
+    /// 连接 X Server 并检查 DPMS 扩展是否可用
+    ///
+    /// # Arguments
+    /// * `config` - `[display.dpms]` 配置段，未指定 `display` 时使用 `DISPLAY` 环境变量
+    pub fn new(config: &DpmsConfig) -> Result<Self, String> {
+        let (conn, screen) = x11rb::connect(config.display.as_deref())
+            .map_err(|e| format!("无法连接 X Server: {}", e))?;
+
+        let present = conn
+            .extension_information(dpms::X11_EXTENSION_NAME)
+            .map_err(|e| format!("查询 DPMS 扩展失败: {}", e))?
+            .is_some();
+        if !present {
+            return Err("X Server 未启用 DPMS 扩展".to_string());
+        }
+
+        let capable = conn
+            .dpms_capable()
+            .map_err(|e| format!("查询 DPMS 能力失败: {}", e))?
+            .reply()
+            .map_err(|e| format!("查询 DPMS 能力失败: {}", e))?
+            .capable;
+        if !capable {
+            return Err("当前显示器不支持 DPMS".to_string());
+        }
+
+        Ok(Self {
+            conn,
+            screen,
+            off_level: config.off_level,
+            enabled_by_us: AtomicBool::new(false),
+        })
+    }
+
+    /// 强制设置 DPMS 电源等级
+    ///
+    /// X Server 只在启用 DPMS 时接受强制设置。DPMS 被禁用（如 `xset -dpms`）时显示器始终开启：
+    /// 开启指令无需操作；关闭指令临时启用 DPMS，并保持启用到下一次强制开启之后再禁用，
+    /// 以保留用户关闭自动熄屏的设置。
+    pub fn force_level(&self, level: DpmsLevel) -> Result<(), String> {
+        let enabled = self.power_level()?.is_some();
+        if !enabled {
+            if level == DpmsLevel::On {
+                return Ok(());
+            }
+            self.conn
+                .dpms_enable()
+                .map_err(|e| format!("启用 DPMS 失败: {}", e))?
+                .check()
+                .map_err(|e| format!("启用 DPMS 失败: {}", e))?;
+            self.enabled_by_us.store(true, Ordering::Relaxed);
+        }
+        self.conn
+            .dpms_force_level(to_mode(level))
+            .map_err(|e| format!("设置 DPMS 电源等级失败: {}", e))?
+            .check()
+            .map_err(|e| format!("设置 DPMS 电源等级失败: {}", e))?;
+        if level == DpmsLevel::On && self.enabled_by_us.swap(false, Ordering::Relaxed) {
+            self.conn
+                .dpms_disable()
+                .map_err(|e| format!("恢复禁用 DPMS 失败: {}", e))?
+                .check()
+                .map_err(|e| format!("恢复禁用 DPMS 失败: {}", e))?;
+        }
+        Ok(())
+    }
+
+    /// 读取当前 DPMS 电源等级
+    ///
+    /// # Returns
+    /// * `Result<Option<DpmsLevel>, String>` - DPMS 被禁用时返回 `None`（显示器始终保持开启）
+    pub fn power_level(&self) -> Result<Option<DpmsLevel>, String> {
+        let info = self
+            .conn
+            .dpms_info()
+            .map_err(|e| format!("查询 DPMS 状态失败: {}", e))?
+            .reply()
+            .map_err(|e| format!("查询 DPMS 状态失败: {}", e))?;
+        if !info.state {
+            return Ok(None);
+        }
+        Ok(Some(from_mode(info.power_level)))
+    }
+}
+
+impl DisplayBackend for DpmsBackend {
+    fn name(&self) -> &'static str {
+        "dpms"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            power: true,
+            query_state: true,
+            brightness: false,
+            per_display: false,
+        }
+    }
+
+    fn set_power(&self, on: bool) -> Result<(), String> {
+        self.force_level(if on { DpmsLevel::On } else { self.off_level })
+    }
+
+    fn query_state(&self) -> ScreenState {
+        match self.power_level() {
+            Ok(None) | Ok(Some(DpmsLevel::On)) => ScreenState::On,
+            Ok(Some(_)) => ScreenState::Off,
+            Err(e) => {
+                crate::log_warn(&e);
+                ScreenState::Unknown
+            }
+        }
+    }
+
+    /// DPMS 作用于整个 X Screen，因此只报告一台显示器
+    fn displays(&self) -> Result<Vec<DisplayInfo>, String> {
+        let screen = self
+            .conn
+            .setup()
+            .roots
+            .get(self.screen)
+            .ok_or_else(|| format!("X Screen {} 不存在", self.screen))?;
+        Ok(vec![DisplayInfo {
+            index: 0,
+            name: format!("X Screen {}", self.screen),
+            serial: None,
+            connector: None,
+            resolution: Some(Resolution {
+                width: screen.width_in_pixels as u32,
+                height: screen.height_in_pixels as u32,
+            }),
+        }])
+    }
+}
+
+fn to_mode(level: DpmsLevel) -> DPMSMode {
+    match level {
+        DpmsLevel::On => DPMSMode::ON,
+        DpmsLevel::Standby => DPMSMode::STANDBY,
+        DpmsLevel::Suspend => DPMSMode::SUSPEND,
+        DpmsLevel::Off => DPMSMode::OFF,
+    }
+}
+
+fn from_mode(mode: DPMSMode) -> DpmsLevel {
+    match mode {
+        DPMSMode::STANDBY => DpmsLevel::Standby,
+        DPMSMode::SUSPEND => DpmsLevel::Suspend,
+        DPMSMode::OFF => DpmsLevel::Off,
+        _ => DpmsLevel::On,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mode_round_trip() {
+        for level in [DpmsLevel::On, DpmsLevel::Standby, DpmsLevel::Suspend, DpmsLevel::Off] {
+            assert_eq!(from_mode(to_mode(level)), level);
+        }
+    }
+
+    /// 在真实的 X Server 上关闭再开启显示器，需要 `DISPLAY` 并通过 `cargo test -- --ignored` 运行
+    #[test]
+    #[ignore = "会关闭当前 X Server 的显示器"]
+    fn force_off_and_on() {
+        if std::env::var_os("DISPLAY").is_none() {
+            return;
+        }
+        let backend = DpmsBackend::new(&DpmsConfig::default()).unwrap();
+        let dpms_enabled = backend.power_level().unwrap().is_some();
+
+        backend.set_power(false).unwrap();
+        assert_eq!(backend.power_level().unwrap(), Some(DpmsLevel::Off));
+        assert_eq!(backend.query_state(), ScreenState::Off);
+
+        backend.set_power(true).unwrap();
+        assert_eq!(backend.query_state(), ScreenState::On);
+        // 开启后恢复原来的 DPMS 启用状态
+        assert_eq!(backend.power_level().unwrap().is_some(), dpms_enabled);
+    }
+}
@@ -0,0 +1,394 @@
+use std::ffi::c_void;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Mutex, OnceLock};
+use windows::core::PCWSTR;
+use windows::Win32::Devices::Display::{
+    DestroyPhysicalMonitors, GetNumberOfPhysicalMonitorsFromHMONITOR, GetPhysicalMonitorsFromHMONITOR,
+    GetVCPFeatureAndVCPFeatureReply, SetVCPFeature, PHYSICAL_MONITOR,
+};
+use windows::Win32::Foundation::{GetLastError, BOOL, ERROR_TIMEOUT, HANDLE, LPARAM, RECT, TRUE, WPARAM};
+use windows::Win32::Graphics::Gdi::{
+    EnumDisplayDevicesW, EnumDisplayMonitors, GetMonitorInfoW, DISPLAY_DEVICEW, HDC, HMONITOR, MONITORINFO, MONITORINFOEXW,
+};
+use windows::Win32::System::Registry::{RegGetValueW, HKEY_LOCAL_MACHINE, RRF_RT_REG_BINARY};
+use windows::Win32::System::Power::{
+    PowerSettingRegisterNotification, DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS, POWERBROADCAST_SETTING,
+};
+use windows::Win32::System::SystemServices::GUID_CONSOLE_DISPLAY_STATE;
+use windows::Win32::UI::WindowsAndMessaging::{
+    SendMessageTimeoutW, DEVICE_NOTIFY_CALLBACK, HWND_BROADCAST, PBT_POWERSETTINGCHANGE,
+    SC_MONITORPOWER, WM_SYSCOMMAND, SMTO_ABORTIFHUNG,
+};
+
+use super::{edid_serial, Capabilities, DisplayBackend, DisplayInfo, Resolution, ScreenState};
+
+const STATE_UNKNOWN: u8 = 0;
+const STATE_ON: u8 = 1;
+const STATE_OFF: u8 = 2;
+
+/// 系统通过 `GUID_CONSOLE_DISPLAY_STATE` 报告的显示器状态
+static CONSOLE_DISPLAY_STATE: AtomicU8 = AtomicU8::new(STATE_UNKNOWN);
+
+/// 电源设置通知是否注册成功（整个进程只注册一次）
+static NOTIFICATION_REGISTERED: OnceLock<bool> = OnceLock::new();
+
+/// `GUID_CONSOLE_DISPLAY_STATE` 变化回调
+///
+/// 数据取值：0 关闭，1 开启，2 变暗（仍视为开启）。
+unsafe extern "system" fn on_power_setting_change(
+    _context: *const c_void,
+    event_type: u32,
+    setting: *const c_void,
+) -> u32 {
+    if event_type == PBT_POWERSETTINGCHANGE && !setting.is_null() {
+        let setting = &*(setting as *const POWERBROADCAST_SETTING);
+        if setting.PowerSetting == GUID_CONSOLE_DISPLAY_STATE && setting.DataLength >= 1 {
+            let state = if setting.Data[0] == 0 { STATE_OFF } else { STATE_ON };
+            CONSOLE_DISPLAY_STATE.store(state, Ordering::Relaxed);
+        }
+    }
+    0
+}
+
+/// 订阅显示器状态变化通知，注册后系统会立即回调一次当前状态
+fn register_display_state_notification() -> bool {
+    *NOTIFICATION_REGISTERED.get_or_init(|| unsafe {
+        // 订阅参数在整个进程生命周期内都需要有效
+        let params: &'static mut DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS =
+            Box::leak(Box::new(DEVICE_NOTIFY_SUBSCRIBE_PARAMETERS {
+                Callback: Some(on_power_setting_change),
+                Context: std::ptr::null_mut(),
+            }));
+        let mut handle: *mut c_void = std::ptr::null_mut();
+        match PowerSettingRegisterNotification(
+            &GUID_CONSOLE_DISPLAY_STATE,
+            DEVICE_NOTIFY_CALLBACK,
+            HANDLE(params as *mut _ as isize),
+            &mut handle,
+        ) {
+            Ok(_) => true,
+            Err(e) => {
+                crate::log_warn(&format!("注册显示器状态通知失败，将使用内部状态跟踪: {}", e));
+                false
+            }
+        }
+    })
+}
+
+/// VCP 0xD6：显示器电源模式
+const VCP_POWER_MODE: u8 = 0xD6;
+/// VCP 0xD6 取值：开启
+const POWER_MODE_ON: u32 = 1;
+/// VCP 0xD6 取值：关闭（DPM Off）
+const POWER_MODE_OFF: u32 = 4;
+
+/// `EnumDisplayDevicesW` 标志：`DeviceID` 返回设备接口路径而不是硬件 ID
+const EDD_GET_DEVICE_INTERFACE_NAME: u32 = 0x1;
+
+/// 截取 UTF-16 字符串中第一个 NUL 之前的部分
+fn from_wide(buf: &[u16]) -> String {
+    let len = buf.iter().position(|&c| c == 0).unwrap_or(buf.len());
+    String::from_utf16_lossy(&buf[..len])
+}
+
+/// 转换为以 NUL 结尾的 UTF-16 字符串
+fn to_wide(text: &str) -> Vec<u16> {
+    text.encode_utf16().chain(Some(0)).collect()
+}
+
+/// 从注册表读取显示器的 EDID
+///
+/// 显示器的设备接口路径（如 `\\?\DISPLAY#DEL41A3#5&1a2b3c&0&UID4353#{e6f07b5f-...}`）
+/// 对应 `HKLM\SYSTEM\CurrentControlSet\Enum\DISPLAY\DEL41A3\5&1a2b3c&0&UID4353`，
+/// 系统读取到的 EDID 保存在其下 `Device Parameters` 的 `EDID` 值中。
+fn read_edid(interface: &str) -> Option<Vec<u8>> {
+    let parts: Vec<&str> = interface.trim_start_matches(r"\\?\").split('#').take(3).collect();
+    if parts.len() < 3 {
+        return None;
+    }
+    let key = to_wide(&format!(r"SYSTEM\CurrentControlSet\Enum\{}\Device Parameters", parts.join(r"\")));
+    let value = to_wide("EDID");
+    let mut edid = vec![0u8; 256];
+    let mut size = edid.len() as u32;
+    unsafe {
+        RegGetValueW(
+            HKEY_LOCAL_MACHINE,
+            PCWSTR(key.as_ptr()),
+            PCWSTR(value.as_ptr()),
+            RRF_RT_REG_BINARY,
+            None,
+            Some(edid.as_mut_ptr() as *mut _),
+            Some(&mut size),
+        )
+        .ok()
+        .ok()?;
+    }
+    edid.truncate(size as usize);
+    (edid.len() >= 128).then_some(edid)
+}
+
+/// 逻辑显示器（如 "DISPLAY1"）下各台显示器的 EDID 序列号，顺序与 `EnumDisplayDevicesW` 一致
+fn monitor_serials(device: &str) -> Vec<Option<String>> {
+    let adapter = to_wide(&format!(r"\\.\{}", device));
+    let mut serials = Vec::new();
+    for index in 0.. {
+        let mut info = DISPLAY_DEVICEW {
+            cb: std::mem::size_of::<DISPLAY_DEVICEW>() as u32,
+            ..Default::default()
+        };
+        let ok = unsafe { EnumDisplayDevicesW(PCWSTR(adapter.as_ptr()), index, &mut info, EDD_GET_DEVICE_INTERFACE_NAME) };
+        if !ok.as_bool() {
+            break;
+        }
+        serials.push(read_edid(&from_wide(&info.DeviceID)).and_then(|edid| edid_serial(&edid)));
+    }
+    serials
+}
+
+/// 通过 DDC/CI 访问的物理显示器，句柄在 drop 时释放
+struct PhysicalMonitor {
+    /// 所属逻辑显示器的设备名（去掉 `\\.\` 前缀），如 "DISPLAY1"
+    device: String,
+    /// 所属逻辑显示器的分辨率
+    resolution: Option<Resolution>,
+    /// EDID 序列号，注册表中没有 EDID 时为 `None`
+    serial: Option<String>,
+    monitor: PHYSICAL_MONITOR,
+}
+
+impl PhysicalMonitor {
+    fn description(&self) -> String {
+        from_wide(&self.monitor.szPhysicalMonitorDescription)
+    }
+
+    fn set_power(&self, on: bool) -> Result<(), String> {
+        let value = if on { POWER_MODE_ON } else { POWER_MODE_OFF };
+        if unsafe { SetVCPFeature(self.monitor.hPhysicalMonitor, VCP_POWER_MODE, value) } == 0 {
+            return Err(format!(
+                "显示器 {} 写入 VCP 0xD6 失败: {}",
+                self.device,
+                windows::core::Error::from_win32()
+            ));
+        }
+        Ok(())
+    }
+
+    fn state(&self) -> ScreenState {
+        let mut current = 0u32;
+        let ok = unsafe {
+            GetVCPFeatureAndVCPFeatureReply(self.monitor.hPhysicalMonitor, VCP_POWER_MODE, None, &mut current, None)
+        };
+        match (ok, current) {
+            (0, _) => ScreenState::Unknown,
+            (_, POWER_MODE_ON) => ScreenState::On,
+            _ => ScreenState::Off,
+        }
+    }
+}
+
+impl Drop for PhysicalMonitor {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = DestroyPhysicalMonitors(std::slice::from_ref(&self.monitor));
+        }
+    }
+}
+
+/// `EnumDisplayMonitors` 回调，把逻辑显示器句柄收集到 `data` 指向的 Vec 中
+unsafe extern "system" fn collect_monitor(hmonitor: HMONITOR, _hdc: HDC, _rect: *mut RECT, data: LPARAM) -> BOOL {
+    let monitors = &mut *(data.0 as *mut Vec<HMONITOR>);
+    monitors.push(hmonitor);
+    TRUE
+}
+
+/// 逻辑显示器的设备名（如 "DISPLAY1"）与分辨率
+fn monitor_info(hmonitor: HMONITOR) -> (String, Option<Resolution>) {
+    let mut info = MONITORINFOEXW::default();
+    info.monitorInfo.cbSize = std::mem::size_of::<MONITORINFOEXW>() as u32;
+    let ok = unsafe { GetMonitorInfoW(hmonitor, &mut info as *mut MONITORINFOEXW as *mut MONITORINFO) };
+    if !ok.as_bool() {
+        return (String::new(), None);
+    }
+    let rect = info.monitorInfo.rcMonitor;
+    let resolution = Resolution {
+        width: (rect.right - rect.left) as u32,
+        height: (rect.bottom - rect.top) as u32,
+    };
+    (from_wide(&info.szDevice).trim_start_matches(r"\\.\").to_string(), Some(resolution))
+}
+
+/// 枚举所有支持 DDC/CI 的物理显示器，顺序与 `EnumDisplayMonitors` 一致
+///
+/// # Arguments
+/// * `read_serials` - 是否从注册表读取 EDID 序列号，只有列出显示器时需要
+fn physical_monitors(read_serials: bool) -> Result<Vec<PhysicalMonitor>, String> {
+    let mut handles: Vec<HMONITOR> = Vec::new();
+    let ok = unsafe {
+        EnumDisplayMonitors(
+            HDC::default(),
+            None,
+            Some(collect_monitor),
+            LPARAM(&mut handles as *mut Vec<HMONITOR> as isize),
+        )
+    };
+    if !ok.as_bool() {
+        return Err(format!("EnumDisplayMonitors 失败: {}", windows::core::Error::from_win32()));
+    }
+
+    let mut monitors = Vec::new();
+    for hmonitor in handles {
+        let (device, resolution) = monitor_info(hmonitor);
+        let mut count = 0u32;
+        let mut physical = unsafe {
+            if let Err(e) = GetNumberOfPhysicalMonitorsFromHMONITOR(hmonitor, &mut count) {
+                crate::log_warn(&format!("获取显示器 {} 的物理显示器数量失败: {}", device, e));
+                continue;
+            }
+            vec![PHYSICAL_MONITOR::default(); count as usize]
+        };
+        if let Err(e) = unsafe { GetPhysicalMonitorsFromHMONITOR(hmonitor, &mut physical) } {
+            crate::log_warn(&format!("获取显示器 {} 的物理显示器句柄失败: {}", device, e));
+            continue;
+        }
+        // 同一逻辑显示器下（如复制模式）的物理显示器假定与 EnumDisplayDevicesW 的顺序一致
+        let serials = if read_serials { monitor_serials(&device) } else { Vec::new() };
+        monitors.extend(physical.into_iter().enumerate().map(|(i, monitor)| PhysicalMonitor {
+            device: device.clone(),
+            resolution,
+            serial: serials.get(i).cloned().flatten(),
+            monitor,
+        }));
+    }
+    Ok(monitors)
+}
+
+/// Windows 广播后端
+///
+/// 通过 `HWND_BROADCAST` 向所有顶层窗口发送 `SC_MONITORPOWER` 消息，
+/// 由系统统一控制所有显示器的电源状态；
+/// 实际状态来自系统的 `GUID_CONSOLE_DISPLAY_STATE` 通知，
+/// 因此系统自行熄屏后也能得到正确状态。
+///
+/// 指定单个显示器时无法使用广播，改为通过 DDC/CI（Dxva2 Monitor Configuration API）
+/// 写入该显示器的 VCP 0xD6 电源模式，要求显示器开启 DDC/CI。
+/// 显示器序列号来自系统保存在注册表中的 EDID。
+pub struct Win32Backend {
+    state_notification: bool,
+    /// 最近一次枚举到的物理显示器，每条指令解析目标显示器时刷新，
+    /// 同一条指令内的逐台操作与状态确认不再重复枚举
+    monitors: Mutex<Vec<PhysicalMonitor>>,
+}
+
+impl Win32Backend {
+    pub fn new() -> Self {
+        Win32Backend {
+            state_notification: register_display_state_notification(),
+            monitors: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// 对指定序号的物理显示器执行操作，缓存中没有该序号时重新枚举
+    fn with_monitor<T>(&self, index: usize, f: impl FnOnce(&PhysicalMonitor) -> Result<T, String>) -> Result<T, String> {
+        let mut monitors = self.monitors.lock().unwrap();
+        if index >= monitors.len() {
+            *monitors = physical_monitors(false)?;
+        }
+        let monitor = monitors.get(index).ok_or_else(|| format!("显示器序号 {} 不存在", index))?;
+        f(monitor)
+    }
+}
+
+impl DisplayBackend for Win32Backend {
+    fn name(&self) -> &'static str {
+        "win32"
+    }
+
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            power: true,
+            // SC_MONITORPOWER 只能下发指令，实际状态依赖系统通知
+            query_state: self.state_notification,
+            brightness: false,
+            per_display: true,
+        }
+    }
+
+    /// 控制显示器电源状态
+    ///
+    /// # Safety
+    /// 此函数包含 unsafe 代码块，因为调用了 Windows API。
+    /// 在 Windows 系统上调用是安全的。
+    fn set_power(&self, on: bool) -> Result<(), String> {
+        unsafe {
+            // 根据开启/关闭状态设置显示器电源参数
+            // -1: 显示器开启
+            // 2: 显示器关闭
+            let state = if on { -1 } else { 2 };
+            // 使用 SendMessageTimeoutW 防止 HWND_BROADCAST 导致阻塞
+            // 设置较短的超时（例如 500ms），并在窗口挂起时中止
+            let mut _unused: usize = 0;
+            let result = SendMessageTimeoutW(
+                HWND_BROADCAST,
+                WM_SYSCOMMAND,
+                WPARAM(SC_MONITORPOWER as usize),
+                LPARAM(state),
+                SMTO_ABORTIFHUNG,
+                500,
+                Some(&mut _unused as *mut usize),
+            );
+
+            if result.0 == 0 {
+                if let Err(e) = GetLastError() {
+                    // 广播时个别窗口超时不影响系统处理该消息，是否生效交由状态确认判断
+                    if e.code() != ERROR_TIMEOUT.to_hresult() {
+                        return Err(format!("SendMessageTimeoutW 失败: {}", e));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn query_state(&self) -> ScreenState {
+        match CONSOLE_DISPLAY_STATE.load(Ordering::Relaxed) {
+            STATE_ON => ScreenState::On,
+            STATE_OFF => ScreenState::Off,
+            _ => ScreenState::Unknown,
+        }
+    }
+
+    fn displays(&self) -> Result<Vec<DisplayInfo>, String> {
+        let monitors = physical_monitors(true)?;
+        let displays = monitors
+            .iter()
+            .enumerate()
+            .map(|(index, monitor)| DisplayInfo {
+                index,
+                name: monitor.description(),
+                serial: monitor.serial.clone(),
+                connector: Some(monitor.device.clone()),
+                resolution: monitor.resolution,
+            })
+            .collect();
+        // 替换缓存时释放上一次枚举的句柄
+        *self.monitors.lock().unwrap() = monitors;
+        Ok(displays)
+    }
+
+    fn set_power_for(&self, displays: &[usize], on: bool) -> Result<(), String> {
+        for &index in displays {
+            self.with_monitor(index, |monitor| monitor.set_power(on))?;
+        }
+        Ok(())
+    }
+
+    fn query_state_for(&self, display: usize) -> ScreenState {
+        match self.with_monitor(display, |monitor| Ok(monitor.state())) {
+            Ok(state) => state,
+            Err(e) => {
+                crate::log_warn(&e);
+                ScreenState::Unknown
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+
+use crate::mqtt::MqttConfig;
+
+/// 解析指令时间戳：RFC3339 字符串或 Unix 毫秒时间戳
+pub fn parse(ts: &Value) -> Result<DateTime<Utc>, String> {
+    match ts {
+        Value::String(text) => DateTime::parse_from_rfc3339(text)
+            .map(|time| time.with_timezone(&Utc))
+            .map_err(|e| format!("不是有效的 RFC3339 时间 '{}': {}", text, e)),
+        Value::Number(ms) => ms
+            .as_i64()
+            .and_then(DateTime::from_timestamp_millis)
+            .ok_or_else(|| format!("不是有效的毫秒时间戳: {}", ms)),
+        _ => Err(format!("必须是 RFC3339 字符串或毫秒时间戳: {}", ts)),
+    }
+}
+
+/// 根据 `params.ts` 检查指令是否过期，未带 `params.ts` 时使用签名指令的 `ts`
+///
+/// 持久会话或保留消息可能在很久之后才送达，过期的指令不再执行。
+/// 两个时间戳都未携带的指令总是有效。
+///
+/// # Arguments
+/// * `params` - 指令参数
+/// * `ts` - 指令顶层的 `ts` 字段
+/// * `config` - `[mqtt]` 配置，提供最长有效时间与时钟误差
+/// * `now` - 当前时间
+///
+/// # Returns
+/// * `Err(String)` - 指令已过期、时间超前过多或时间戳格式错误
+pub fn check_command_age(
+    params: Option<&Value>,
+    ts: Option<&Value>,
+    config: &MqttConfig,
+    now: DateTime<Utc>,
+) -> Result<(), String> {
+    if config.max_command_age_secs == 0 {
+        return Ok(());
+    }
+    let (name, ts) = match (params.and_then(|p| p.get("ts")), ts) {
+        (Some(ts), _) => ("params.ts", ts),
+        (None, Some(ts)) => ("ts", ts),
+        (None, None) => return Ok(()),
+    };
+    let sent = parse(ts).map_err(|e| format!("{} {}", name, e))?;
+    let age = now.signed_duration_since(sent);
+    if age > chrono::Duration::seconds(config.max_command_age_secs as i64) {
+        return Err(format!("指令已过期 (发送于 {}，已过 {} 秒)", sent.to_rfc3339(), age.num_seconds()));
+    }
+    if -age > chrono::Duration::seconds(config.max_clock_skew_secs as i64) {
+        return Err(format!("指令时间超前本机 {} 秒 (发送于 {})", -age.num_seconds(), sent.to_rfc3339()));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use serde_json::json;
+
+    #[test]
+    fn parses_rfc3339_and_millis() {
+        let rfc = parse(&json!("2024-05-01T08:00:00+08:00")).unwrap();
+        let millis = parse(&json!(1_714_521_600_000_i64)).unwrap();
+        assert_eq!(rfc, millis);
+        assert!(parse(&json!("yesterday")).is_err());
+        assert!(parse(&json!(true)).is_err());
+    }
+
+    #[test]
+    fn command_age_prefers_params_ts() {
+        let config = MqttConfig {
+            max_command_age_secs: 60,
+            max_clock_skew_secs: 5,
+            ..MqttConfig::default()
+        };
+        let now = Utc::now();
+        let sent = |offset: Duration| json!({ "ts": (now - offset).to_rfc3339() });
+
+        assert!(check_command_age(Some(&sent(Duration::seconds(59))), None, &config, now).is_ok());
+        let err = check_command_age(Some(&sent(Duration::seconds(61))), None, &config, now).unwrap_err();
+        assert!(err.contains("已过期"), "{}", err);
+        // 发送端时钟略快时仍然接受，超过 max_clock_skew_secs 时拒绝
+        assert!(check_command_age(Some(&sent(Duration::seconds(-5))), None, &config, now).is_ok());
+        let err = check_command_age(Some(&sent(Duration::seconds(-6))), None, &config, now).unwrap_err();
+        assert!(err.contains("超前本机 6 秒"), "{}", err);
+
+        let err = check_command_age(Some(&json!({ "ts": "soon" })), None, &config, now).unwrap_err();
+        assert!(err.starts_with("params.ts"), "{}", err);
+        assert!(check_command_age(Some(&json!({ "level": 30 })), None, &config, now).is_ok());
+        assert!(check_command_age(None, None, &config, now).is_ok());
+
+        // 签名指令未带 params.ts 时按顶层 ts 检查
+        let old = json!((now - Duration::seconds(61)).timestamp_millis());
+        let err = check_command_age(None, Some(&old), &config, now).unwrap_err();
+        assert!(err.contains("已过期"), "{}", err);
+        assert!(check_command_age(Some(&sent(Duration::zero())), Some(&old), &config, now).is_ok());
+
+        let disabled = MqttConfig::default();
+        assert!(check_command_age(Some(&sent(Duration::days(1))), None, &disabled, now).is_ok());
+    }
+}